@@ -0,0 +1,94 @@
+use crate::{
+    board::{Board, ParsingError},
+    computer::{self, SearchOptions},
+    game::Game,
+    perft,
+};
+
+use std::time::{Duration, Instant};
+
+// A fixed suite of 8x8 positions, from the opening to the late middle game, as the sequences of
+// moves reaching them.
+const POSITIONS: [&str; 8] = [
+    "d3c3f5f6b3d2c2b2f7d6",
+    "d3c5b6d2c4c3c2f3f4g3d1b2a2e1e3b5",
+    "d3e3f6c5b5b6f3f2e2d2c1d1c3b4e1g3c2g7h4g1",
+    "d3c5f6f3b5c6e3a4b7d7g2f5g6e2d1g7e6f2d6d2c4h7c7f7",
+    "d3c5d6e3f5e7c4f4f6g5e2c2h6h5c6b5b6g3a4e1f3c3d1b4f2e6e8d8",
+    "d3e3f3e2f5e6f1c3f7e1d1e7c2f4d7b1b3d2c4b2c1g1g4e8f2g2a2h5h1g8a1d6",
+    "d3e3f5c3c2c1b1a1f2f3c4g6h7c6f4g3h3c5b4b3b5g2f1e2e6g4a3d7g5b6e8h2e7a4a2h6",
+    "d3c3f5f4b3e6d7c5e3d2e1c1c4c2f3g4b1e2f2d1h4g1f1f7g8a1b6b4h1d6b2g3h3a7f6a3a5g7e7a2",
+];
+
+/// Return the positions of the benchmark suite.
+pub fn positions() -> Vec<Board> {
+    POSITIONS.iter().map(|sequence| {
+        let mut game = Game::new(Board::new(8));
+        game.play_sequence(sequence).expect("the positions of the suite are legal");
+        game.board().clone()
+    }).collect()
+}
+
+/// Count the leaves of the 8x8 game to `perft_depth`, and search every position of the suite to
+/// `depth` on a single thread, with the discs of the boards kept as 64-bit words and as generic
+/// bitmaps. Then search the suite on a single thread and on `threads` threads. Print the times and
+/// the speedups.
+pub fn run(depth: u8, threads: usize, perft_depth: u8) -> Result<(), ParsingError> {
+    let mut times = [Duration::ZERO; 2];
+    let mut leaves = [0; 2];
+    for (i, board) in [Board::new(8), Board::new(8).to_generic()].iter().enumerate() {
+        let start = Instant::now();
+        leaves[i] = perft::perft(board, perft_depth);
+        times[i] = start.elapsed();
+    }
+    assert_eq!(leaves[0], leaves[1], "the 64-bit words and the generic bitmaps disagree");
+    println!("Perft {}: {} leaves, {:.3} s with 64-bit words, {:.3} s with generic bitmaps, speedup {:.2}.",
+        perft_depth, leaves[0], times[0].as_secs_f64(), times[1].as_secs_f64(), speedup([times[1], times[0]]));
+
+    let mut times = [Duration::ZERO; 2];
+    for board in positions() {
+        let options = SearchOptions { timeout: None, depth: Some(depth), seed: Some(0), ..Default::default() };
+        for (i, board) in [board.clone(), board.to_generic()].iter().enumerate() {
+            let start = Instant::now();
+            computer::search(board, &options);
+            times[i] += start.elapsed();
+        }
+    }
+    println!("Search: {:.3} s with 64-bit words, {:.3} s with generic bitmaps, speedup {:.2}.",
+        times[0].as_secs_f64(), times[1].as_secs_f64(), speedup([times[1], times[0]]));
+
+    let mut totals = [Duration::ZERO; 2];
+    for (i, board) in positions().iter().enumerate() {
+        let mut times = [Duration::ZERO; 2];
+        let mut nodes = [0; 2];
+        for (j, threads) in [1, threads].into_iter().enumerate() {
+            let options = SearchOptions { timeout: None, depth: Some(depth), seed: Some(0), threads, ..Default::default() };
+            let start = Instant::now();
+            nodes[j] = computer::search(board, &options).nodes;
+            times[j] = start.elapsed();
+            totals[j] += times[j];
+        }
+        println!("Position {} ({} empties): {:.3} s ({} nodes, {:.0} nodes/s) on 1 thread, {:.3} s ({} nodes) on {}, speedup {:.2}.",
+            i + 1, board.empty_count(), times[0].as_secs_f64(), nodes[0], nodes[0] as f64 / times[0].as_secs_f64().max(f64::EPSILON),
+            times[1].as_secs_f64(), nodes[1], threads, speedup(times));
+    }
+    println!("Total: {:.3} s on 1 thread, {:.3} s on {}, speedup {:.2}.",
+        totals[0].as_secs_f64(), totals[1].as_secs_f64(), threads, speedup(totals));
+    Ok(())
+}
+
+fn speedup(times: [Duration; 2]) -> f64 {
+    times[0].as_secs_f64() / times[1].as_secs_f64().max(f64::EPSILON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suite() {
+        let boards = positions();
+        assert_eq!(boards.len(), POSITIONS.len());
+        assert!(boards.iter().all(|b| b.player.is_some() && b.empty_count() >= 20));
+    }
+}
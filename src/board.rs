@@ -1,7 +1,17 @@
-mod bitmap;
+pub mod bitmap;
+mod fast;
+mod ggf;
+mod symmetry;
+mod zobrist;
 use bitmap::Bitmap;
+pub use ggf::parse_size;
+pub use symmetry::Transform;
+
+/// The largest number of columns or rows of a board.
+pub const MAX_SIZE: u8 = 14;
 
 use std::{
+    fmt,
     fs::File,
     io::{BufReader, Read, stdout},
     str::FromStr,
@@ -30,9 +40,29 @@ impl Player {
     }
 }
 
+/// A square to play on, written as its column letter followed by its row number starting from 1,
+/// as in `A1` for the top left corner and `B3` for the square below it on the second column.
+/// Parsing is case-insensitive.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Move { x: u8, y: u8, }
 
+impl Move {
+    /// Create a `Move` at the given column and row, starting from 0.
+    pub fn new(x: u8, y: u8) -> Self {
+        Self { x, y }
+    }
+
+    /// Return the column of the move, starting from 0.
+    pub fn x(&self) -> u8 {
+        self.x
+    }
+
+    /// Return the row of the move, starting from 0.
+    pub fn y(&self) -> u8 {
+        self.y
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ParsingError {
     IOError,
@@ -42,132 +72,295 @@ pub enum ParsingError {
     PlayerParseError(char),
     BadSize,
     InconsistentSize,
+    UnknownField(String),
+    InvalidMove(String),
+    IllegalMove(usize),
+    InvalidField(String, String),
+    MissingField(String),
+    UnterminatedRecord,
 }
 
 /// A structure representing a board state.
-// It has a bitmap for the discs of each player and for the moves of the current player.
+// It has the discs of each player and the moves of the current player.
 // It keeps track of the current player, or if the game is over.
-// The `black`, `white`, and `moves` bitmaps are all of size `size.
-// `size` must be an even number between 2 and 10 inclusive.
-#[derive(Debug, Clone, PartialEq)]
+// `width` and `height` must be between 2 and `MAX_SIZE` inclusive.
+#[derive(Debug, Clone)]
 pub struct Board {
-    size: u8,
-    black: Bitmap,
-    white: Bitmap,
-    moves: Bitmap,
+    width: u8,
+    height: u8,
+    discs: Discs,
     pub player: Option<Player>,
 }
 
+// The discs of black and white and the moves of the current player.
+// 8x8 boards keep them as 64-bit words, the square at `(x, y)` being the bit `x + 8 * y`, which
+// `play` works on directly. Other boards keep them as bitmaps of `width` columns and `height`
+// rows.
+#[derive(Debug, Clone)]
+enum Discs {
+    Words { black: u64, white: u64, moves: u64 },
+    Bitmaps { black: Bitmap, white: Bitmap, moves: Bitmap },
+}
+
 impl Board {
     /// Create a `Board` with `Black` as the starting player and the 4 checkered discs at the
     /// center of the board.
     /// If the `size` is 2 the game is already over and this is replected in the `player`.
     pub fn new(size: u8) -> Self {
-        assert!(size % 2 == 0 && (2..=10).contains(&size));
-
-        let black = Bitmap::new(size)
-            .set(size / 2, size / 2 - 1)
-            .set(size / 2 - 1, size / 2);
-        let white = Bitmap::new(size)
-            .set(size / 2 - 1, size / 2 - 1)
-            .set(size / 2, size / 2);
+        Self::rectangular(size, size)
+    }
+
+    /// Create a `Board` with `width` columns and `height` rows, with the 4 starting discs at its
+    /// center as in `new`.
+    /// On an odd side, the starting discs are one square closer to the top or left edge than to
+    /// the other.
+    pub fn rectangular(width: u8, height: u8) -> Self {
+        assert!(is_valid_size(width) && is_valid_size(height));
+
+        // The top left corner of the starting discs.
+        let (x, y) = ((width - 1) / 2, (height - 1) / 2);
+        let black = Bitmap::empty_rect(width, height)
+            .set(x + 1, y)
+            .set(x, y + 1);
+        let white = Bitmap::empty_rect(width, height)
+            .set(x, y)
+            .set(x + 1, y + 1);
         let moves = compute_moves(&black, &white);
+        let player = if moves.not_empty() { Some(Player::Black) } else { None };
+        Self::from_bitmaps(black, white, moves, player)
+    }
 
-        Self {
-            size,
-            black,
-            white,
-            moves,
-            player: if size != 2 { Some(Player::Black) } else { None },
+    // Create a board from the bitmaps of its discs and moves, which are converted to 64-bit words
+    // on 8x8 boards.
+    fn from_bitmaps(black: Bitmap, white: Bitmap, moves: Bitmap, player: Option<Player>) -> Self {
+        let (width, height) = (black.width, black.height);
+        let discs = if (width, height) == (8, 8) {
+            Discs::Words { black: black.to_u64(), white: white.to_u64(), moves: moves.to_u64() }
+        } else {
+            Discs::Bitmaps { black, white, moves }
+        };
+        Self { width, height, discs, player }
+    }
+
+    /// Return the same board state, kept as bitmaps whatever its shape, so that `play` uses the
+    /// implementation for all sizes.
+    pub(crate) fn to_generic(&self) -> Self {
+        let (black, white) = self.bitmaps();
+        Self { discs: Discs::Bitmaps { black, white, moves: self.move_bitmap() }, ..self.clone() }
+    }
+
+    // Return the discs of black and white as bitmaps.
+    fn bitmaps(&self) -> (Bitmap, Bitmap) {
+        match &self.discs {
+            Discs::Words { black, white, .. } => (Bitmap::from_u64(*black), Bitmap::from_u64(*white)),
+            Discs::Bitmaps { black, white, .. } => (black.clone(), white.clone()),
         }
     }
 
+    // Return the moves of the current player as a bitmap.
+    fn move_bitmap(&self) -> Bitmap {
+        match &self.discs {
+            Discs::Words { moves, .. } => Bitmap::from_u64(*moves),
+            Discs::Bitmaps { moves, .. } => moves.clone(),
+        }
+    }
+
+    /// Return the number of columns of the board.
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    /// Return the number of rows of the board.
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
     /// Return the state of the square at the given coordinates.
-    fn get(&self, x: u8, y: u8) -> Square {
-        assert!(x < self.size && y < self.size);
-        if self.black.get(x, y) {
+    pub fn get(&self, x: u8, y: u8) -> Square {
+        assert!(x < self.width && y < self.height);
+        let (black, white) = match &self.discs {
+            Discs::Words { black, white, .. } => (black >> (x + 8 * y) & 1 != 0, white >> (x + 8 * y) & 1 != 0),
+            Discs::Bitmaps { black, white, .. } => (black.get(x, y), white.get(x, y)),
+        };
+        if black {
             Square::Disc(Player::Black)
-        } else if self.white.get(x, y) {
+        } else if white {
             Square::Disc(Player::White)
         } else { Square::Empty }
     }
 
     /// Set the state of the square at the given coordinates.
     pub fn set(&self, x: u8, y: u8, squ: Square) -> Self {
-        assert!(x < self.size && y < self.size);
+        assert!(x < self.width && y < self.height);
+        let (black, white) = self.bitmaps();
         let (black, white) = match squ {
             Square::Disc(Player::Black) => (
-                self.black.set(x, y),
-                self.white.unset(x, y)
+                black.set(x, y),
+                white.unset(x, y)
             ),
             Square::Disc(Player::White) => (
-                self.black.unset(x, y),
-                self.white.set(x, y)
+                black.unset(x, y),
+                white.set(x, y)
             ),
             Square::Empty => (
-                self.black.unset(x, y),
-                self.white.unset(x, y)
+                black.unset(x, y),
+                white.unset(x, y)
             ),
         };
 
         let moves = match self.player {
             Some(Player::Black) => compute_moves(&black, &white),
             Some(Player::White) => compute_moves(&white, &black),
-            None => Bitmap::empty(self.size),
+            None => Bitmap::empty_rect(self.width, self.height),
         };
-
-        Self {
-            size: self.size,
-            black,
-            white,
-            moves,
-            player: self.player
-        }
+        Self::from_bitmaps(black, white, moves, self.player)
     }
 
     /// Return the score of the game.
     pub fn score(&self) -> (u8, u8) {
-        (self.black.popcount().try_into().unwrap(),
-        self.white.popcount().try_into().unwrap())
+        let (black, white) = match &self.discs {
+            Discs::Words { black, white, .. } => (black.count_ones(), white.count_ones()),
+            Discs::Bitmaps { black, white, .. } => (black.popcount(), white.popcount()),
+        };
+        (black.try_into().unwrap(), white.try_into().unwrap())
+    }
+
+    /// Return the number of empty squares.
+    pub fn empty_count(&self) -> u8 {
+        let (black, white) = self.score();
+        self.width * self.height - black - white
     }
 
     /// Return a vector of possible `Move`s for the current player.
     pub fn moves(&self) -> Vec<Move> {
-        self.moves.clone().map(|(x, y)| Move { x, y }).collect()
+        match &self.discs {
+            Discs::Words { moves, .. } => fast::squares(*moves).map(|(x, y)| Move { x, y }).collect(),
+            Discs::Bitmaps { moves, .. } => moves.clone().map(|(x, y)| Move { x, y }).collect(),
+        }
+    }
+
+    /// Return the number of possible `Move`s for the current player.
+    pub fn move_count(&self) -> u32 {
+        match &self.discs {
+            Discs::Words { moves, .. } => moves.count_ones(),
+            Discs::Bitmaps { moves, .. } => moves.popcount(),
+        }
+    }
+
+    /// Return the number of squares where the given player could play, whoever's turn it is.
+    pub fn mobility(&self, player: Player) -> u32 {
+        match (&self.discs, player) {
+            (Discs::Words { black, white, .. }, Player::Black) => fast::moves(*black, *white).count_ones(),
+            (Discs::Words { black, white, .. }, Player::White) => fast::moves(*white, *black).count_ones(),
+            (Discs::Bitmaps { black, white, .. }, Player::Black) => compute_moves(black, white).popcount(),
+            (Discs::Bitmaps { black, white, .. }, Player::White) => compute_moves(white, black).popcount(),
+        }
+    }
+
+    /// Return the number of discs of the given player that are next to an empty square.
+    pub fn frontier(&self, player: Player) -> u32 {
+        let (black, white) = self.bitmaps();
+        let empty = black.union(&white).not();
+        let mut next_to_empty = Bitmap::empty_rect(self.width, self.height);
+        for shift in [
+            Bitmap::shift_north, Bitmap::shift_south, Bitmap::shift_east, Bitmap::shift_west,
+            Bitmap::shift_ne, Bitmap::shift_se, Bitmap::shift_sw, Bitmap::shift_nw
+        ] {
+            next_to_empty = next_to_empty.union(&shift(&empty));
+        }
+
+        match player {
+            Player::Black => black,
+            Player::White => white,
+        }.intersection(&next_to_empty).popcount()
+    }
+
+    /// Return the `Bitmap`s of the stable discs of black and white, that can never be flipped.
+    /// Every disc returned is stable, but some stable discs may be missed in rare cases.
+    pub fn stable_discs(&self) -> (Bitmap, Bitmap) {
+        let (black, white) = self.bitmaps();
+        let occupied = black.union(&white);
+        (compute_stable(&black, &occupied), compute_stable(&white, &occupied))
     }
 
     /// Return whether a given `Move` is valid for the current player.
     pub fn is_valid_move(&self, m: &Move) -> bool {
-        m.x < self.size && m.y < self.size
-            && self.moves.get(m.x, m.y)
+        m.x < self.width && m.y < self.height && match &self.discs {
+            Discs::Words { moves, .. } => moves >> (m.x + 8 * m.y) & 1 != 0,
+            Discs::Bitmaps { moves, .. } => moves.get(m.x, m.y),
+        }
     }
 
     /// Place a disc on the board.
+    /// On 8x8 boards, the move is played on the 64-bit words of the discs.
     pub fn play(&self, m: &Move) -> Option<Self> {
-        let (x, y) = (m.x, m.y);
-        if !self.moves.get(x, y) {
+        match &self.discs {
+            &Discs::Words { black, white, .. } => self.play_fast(black, white, m),
+            Discs::Bitmaps { black, white, .. } => self.play_generic(black, white, m),
+        }
+    }
+
+    // Play on the 64-bit words of an 8x8 board.
+    fn play_fast(&self, black: u64, white: u64, m: &Move) -> Option<Self> {
+        if !self.is_valid_move(m) {
             return None;
         }
+        let player = self.player?;
+        let (own, opponent) = match player {
+            Player::Black => (black, white),
+            Player::White => (white, black),
+        };
+
+        let square = m.x as u32 + 8 * m.y as u32;
+        let flipped = fast::flips(own, opponent, square);
+        let (own, opponent) = (own | flipped | 1 << square, opponent & !flipped);
+
+        let (moves, new_player) = match fast::moves(opponent, own) {
+            0 => match fast::moves(own, opponent) {
+                0 => (0, None),
+                moves => (moves, Some(player)),
+            },
+            moves => (moves, Some(player.other())),
+        };
+        let (black, white) = match player {
+            Player::Black => (own, opponent),
+            Player::White => (opponent, own),
+        };
+
+        Some(Self {
+            width: 8,
+            height: 8,
+            discs: Discs::Words { black, white, moves },
+            player: new_player,
+        })
+    }
+
+    // Play on the bitmaps of a board of any size.
+    fn play_generic(&self, black: &Bitmap, white: &Bitmap, m: &Move) -> Option<Self> {
+        if !self.is_valid_move(m) {
+            return None;
+        }
+        let (x, y) = (m.x, m.y);
 
         let (player, opponent) = match self.player? {
-            Player::Black => (&self.black, &self.white),
-            Player::White => (&self.white, &self.black),
+            Player::Black => (black, white),
+            Player::White => (white, black),
         };
 
-        let move_mask = Bitmap::new(self.size).set(x, y);
-        let mut flipped = Bitmap::new(self.size);
+        let move_mask = Bitmap::empty_rect(self.width, self.height).set(x, y);
+        let mut flipped = Bitmap::empty_rect(self.width, self.height);
         for shift in [
             Bitmap::shift_north, Bitmap::shift_south, Bitmap::shift_east, Bitmap::shift_west,
             Bitmap::shift_ne, Bitmap::shift_se, Bitmap::shift_sw, Bitmap::shift_nw
         ] {
-          let mut prev_line = Bitmap::new(self.size);
-          let mut line = shift(&move_mask);
-
-          while line != prev_line && line.subset_of(opponent) {
-            prev_line = line;
-            line = prev_line.union(&shift(&prev_line));
+          // Follow the discs of the opponent from the move, up to the square after them.
+          let mut line = Bitmap::empty_rect(self.width, self.height);
+          let mut next = shift(&move_mask);
+          while next.not_empty() && next.subset_of(opponent) {
+            line = line.union(&next);
+            next = shift(&next);
           }
-          if line.intersection(player).not_empty() {
+          if next.intersection(player).not_empty() {
             flipped = flipped.union(&line);
           }
         }
@@ -182,10 +375,10 @@ impl Board {
             Some(self.player?.other())
         } else {
             moves = compute_moves(&player, &opponent);
-            if compute_moves(&player, &opponent).not_empty() {
+            if moves.not_empty() {
                 Some(self.player?)
             } else {
-                moves = Bitmap::empty(self.size);
+                moves = Bitmap::empty_rect(self.width, self.height);
                 None
             }
         };
@@ -196,10 +389,9 @@ impl Board {
         };
 
         Some(Self {
-            size: self.size,
-            black: new_black,
-            white: new_white,
-            moves,
+            width: self.width,
+            height: self.height,
+            discs: Discs::Bitmaps { black: new_black, white: new_white, moves },
             player: new_player,
         })
     }
@@ -215,14 +407,14 @@ impl Board {
         println!();
 
         print!("  ");
-        for x in 0..self.size {
+        for x in 0..self.width {
             print!(" {}", (b'A' + x) as char);
         }
         println!();
-        for y in 0..self.size {
+        for y in 0..self.height {
             print!("{:2}", y + 1);
-            for x in 0..self.size {
-                if self.moves.get(x, y) {
+            for x in 0..self.width {
+                if self.is_valid_move(&Move { x, y }) {
                     print!(" *");
                 } else {
                     print!(" {}", char::from(self.get(x, y)));
@@ -239,6 +431,19 @@ impl Board {
     }
 }
 
+// Boards are equal when they have the same discs and player to move, whether they keep them as
+// words or as bitmaps.
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        (self.width, self.height, self.player) == (other.width, other.height, other.player) && match (&self.discs, &other.discs) {
+            (Discs::Words { black, white, .. }, Discs::Words { black: other_black, white: other_white, .. }) => {
+                (black, white) == (other_black, other_white)
+            },
+            _ => self.bitmaps() == other.bitmaps(),
+        }
+    }
+}
+
 impl FromStr for Move {
     type Err = ();
 
@@ -258,7 +463,7 @@ impl FromStr for Move {
         let Ok(row): Result<u8, _> = row.parse() else {
             return Err(());
         };
-        if row <= 1 {
+        if row < 1 {
             return Err(());
         }
 
@@ -266,9 +471,15 @@ impl FromStr for Move {
     }
 }
 
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", (self.x + b'A') as char, self.y + 1)
+    }
+}
+
 impl From<Move> for String {
     fn from(m: Move) -> Self {
-        format!("{}{}", (m.y + b'A') as char, m.x + 1)
+        m.to_string()
     }
 }
 
@@ -367,14 +578,28 @@ impl TryFrom<File> for Board {
     type Error = ParsingError;
 
     fn try_from(file: File) -> Result<Self, Self::Error> {
-        let mut chars = BufReader::new(file)
+        let chars = BufReader::new(file)
             .bytes()
             .filter(|r| r.is_ok())
             .map(|c| c.expect("Should be Ok.") as char);
+        Self::parse(chars)
+    }
+}
+
+impl FromStr for Board {
+    type Err = ParsingError;
 
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s.chars())
+    }
+}
+
+impl Board {
+    // Parse a board state from the characters of its string representation.
+    fn parse<T: Iterator<Item = char>>(mut chars: T) -> Result<Self, ParsingError> {
         let player: Player = match next_ignore_chars_and_newlines(&mut chars) {
             Some(c) => Player::try_from(c)?,
-            None => return Err(Self::Error::EmptyFile),
+            None => return Err(ParsingError::EmptyFile),
         };
 
         let mut first_row: Vec<Square> = vec!();
@@ -382,12 +607,12 @@ impl TryFrom<File> for Board {
             match c {
                 '\n' => if first_row.is_empty() { } else { break },
                 'X' | 'O' | '_' => first_row.push(c.try_into().expect("Should be valid character")),
-                _ => return Err(Self::Error::InvalidCharacter(c)),
+                _ => return Err(ParsingError::InvalidCharacter(c)),
             }
         }
         let size = first_row.len();
-        if !(size % 2 == 0 && (2..=10).contains(&size)) {
-            return Err(Self::Error::BadSize)
+        if !size.try_into().is_ok_and(is_valid_size) {
+            return Err(ParsingError::BadSize)
         }
 
         let mut grid: Vec<Vec<Square>> = vec!();
@@ -401,32 +626,38 @@ impl TryFrom<File> for Board {
                         row = vec!();
                     }
                     0 => { },
-                    _ => return Err(Self::Error::InconsistentSize),
+                    _ => return Err(ParsingError::InconsistentSize),
                 },
                 'X' | 'O' | '_' => {
-                    if row.len() < size && grid.len() < size {
+                    if row.len() < size && grid.len() < MAX_SIZE as usize {
                         row.push(c.try_into().expect("Should be valid character"))
-                    } else { return Err(Self::Error::InconsistentSize) }
+                    } else { return Err(ParsingError::InconsistentSize) }
                 },
-                _ => return Err(Self::Error::InvalidCharacter(c)),
+                _ => return Err(ParsingError::InvalidCharacter(c)),
             }
         }
         match row.len() {
             l if l == size => { grid.push(row) },
             0 => { },
-            _ => return Err(Self::Error::InconsistentSize),
+            _ => return Err(ParsingError::InconsistentSize),
         };
 
-        if grid.len() != size {
+        // The number of rows may differ from that of columns, but must be valid too.
+        if !grid.len().try_into().is_ok_and(is_valid_size) {
             return Err(ParsingError::InconsistentSize)
         }
 
-        let size = size.try_into().expect("already checked");
-        let black = grid.iter()
+        Ok(Self::from_grid(&grid, player))
+    }
+
+    // Create a board from a grid of valid shape, with `player` to move if they can, else their
+    // opponent, else with the game over.
+    pub(crate) fn from_grid(grid: &[Vec<Square>], player: Player) -> Self {
+        let black: Bitmap = grid.iter()
             .map(|r| r.iter().map(|s| *s == Square::Disc(Player::Black)).collect())
             .collect::<Vec<Vec<bool>>>()
             .into();
-        let white = grid.iter()
+        let white: Bitmap = grid.iter()
             .map(|r| r.iter().map(|s| *s == Square::Disc(Player::White)).collect())
             .collect::<Vec<Vec<bool>>>()
             .into();
@@ -446,14 +677,7 @@ impl TryFrom<File> for Board {
                 (moves, None)
             }
         };
-
-        Ok(Self {
-            player,
-            size,
-            black,
-            white,
-            moves,
-        })
+        Self::from_bitmaps(black, white, moves, player)
     }
 }
 
@@ -463,8 +687,8 @@ impl From<&Board> for String {
         let mut out = String::new();
         out.push(Square::from(b.player).into());
         out.push('\n');
-        for y in 0..b.size {
-            for x in 0..b.size {
+        for y in 0..b.height {
+            for x in 0..b.width {
                 out.push(b.get(x, y).into());
             }
             out.push('\n');
@@ -493,12 +717,15 @@ fn next_ignore_chars_and_newlines<T: Iterator<Item = char>>(iter: &mut T) -> Opt
     }
 }
 
+// Return whether a board can have `size` columns or rows.
+fn is_valid_size(size: u8) -> bool {
+    (2..=MAX_SIZE).contains(&size)
+}
+
 // Return a `Bitmap` of the possible moves given the `Bitmaps` of the two players.
 fn compute_moves(player: &Bitmap, opponent: &Bitmap) -> Bitmap {
-    assert_eq!(player.size, opponent.size);
-
     let empty = player.union(opponent).not();
-    let mut moves = Bitmap::new(player.size);
+    let mut moves = Bitmap::empty_rect(player.width, player.height);
 
     for shift in [
         Bitmap::shift_north, Bitmap::shift_south, Bitmap::shift_east, Bitmap::shift_west,
@@ -507,19 +734,98 @@ fn compute_moves(player: &Bitmap, opponent: &Bitmap) -> Bitmap {
         let mut candidates = shift(player).intersection(opponent);
 
         while !candidates.is_empty() {
-            moves = shift(&candidates).intersection(&empty).union(&moves);
-            candidates = shift(&candidates).intersection(opponent);
+            let next = shift(&candidates);
+            moves = next.intersection(&empty).union(&moves);
+            candidates = next.intersection(opponent);
         }
     }
 
     moves
 }
 
+// Return a `Bitmap` of the stable discs among `discs`, given the `Bitmap` of all the discs.
+// A disc is stable if, along each of the 4 axes, either the line is full, or one of its neighbors
+// is a wall or a stable disc of the same color.
+fn compute_stable(discs: &Bitmap, occupied: &Bitmap) -> Bitmap {
+    let full = Bitmap::full_rect(discs.width, discs.height);
+    let empty = occupied.not();
+
+    // For each axis, the squares next to a wall and the discs whose line is full.
+    let axes = [
+        (Bitmap::shift_east as fn(&Bitmap) -> Bitmap, Bitmap::shift_west as fn(&Bitmap) -> Bitmap),
+        (Bitmap::shift_north, Bitmap::shift_south),
+        (Bitmap::shift_ne, Bitmap::shift_sw),
+        (Bitmap::shift_nw, Bitmap::shift_se),
+    ].map(|(forward, backward)| {
+        let walls = full.setminus(&forward(&full)).union(&full.setminus(&backward(&full)));
+
+        let mut not_full = empty.clone();
+        loop {
+            let next = forward(&not_full).union(&backward(&not_full)).intersection(occupied).union(&not_full);
+            if next == not_full {
+                break;
+            }
+            not_full = next;
+        }
+
+        (forward, backward, walls.union(&occupied.setminus(&not_full)))
+    });
+
+    let mut stable = Bitmap::empty_rect(discs.width, discs.height);
+    loop {
+        let mut next = discs.clone();
+        for (forward, backward, protected) in &axes {
+            next = next.intersection(&protected.union(&forward(&stable)).union(&backward(&stable)));
+        }
+        if next == stable {
+            return stable;
+        }
+        stable = next;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::io::{SeekFrom, Seek, Write};
 
+    #[test]
+    fn move_notation() {
+        assert_eq!(Move::new(0, 0).to_string(), "A1");
+        assert_eq!(Move::new(1, 2).to_string(), "B3");
+        assert_eq!(Move::new(9, 9).to_string(), "J10");
+        assert_eq!("b3".parse(), Ok(Move::new(1, 2)));
+        assert_eq!("j10".parse(), Ok(Move::new(9, 9)));
+        assert_eq!(Move::new(10, 11).to_string(), "K12");
+        assert_eq!("n14".parse(), Ok(Move::new(13, 13)));
+        for s in ["", "A", "1", "A0", "AA1", "A-1", "1A", "é1"] {
+            assert_eq!(s.parse::<Move>(), Err(()), "{}", s);
+        }
+    }
+
+    #[test]
+    fn move_round_trip() {
+        for size in (2..=MAX_SIZE).step_by(2) {
+            for y in 0..size {
+                for x in 0..size {
+                    let m = Move::new(x, y);
+                    assert_eq!(m.to_string().parse(), Ok(m));
+                    assert_eq!(m.to_string().to_lowercase().parse(), Ok(m));
+                    assert_eq!(String::from(m), m.to_string());
+                }
+            }
+        }
+
+        // The moves printed for the computer can be typed back by humans.
+        let mut board = Board::new(8);
+        while board.player.is_some() {
+            for m in board.moves() {
+                assert!(board.is_valid_move(&m.to_string().parse().unwrap()));
+            }
+            board = board.play(&board.moves()[0]).unwrap();
+        }
+    }
+
     #[test]
     fn printing() {
         Board::new(8).pretty_print();
@@ -537,6 +843,94 @@ mod tests {
         assert_eq!(moves, vec![Move { x: 3, y: 2 }, Move { x: 2, y: 3 }, Move { x: 5, y: 4 }, Move { x: 4, y: 5 }]);
     }
 
+    #[test]
+    fn fast_play() {
+        use rand::{SeedableRng, rngs::StdRng, seq::IndexedRandom};
+
+        // The 8x8 fast path plays like the generic implementation along random games.
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..50 {
+            let mut board = Board::new(8);
+            while board.player.is_some() {
+                let generic = board.to_generic();
+                assert_eq!(board.moves(), generic.moves());
+                assert_eq!(board.hash(), generic.hash());
+                assert_eq!(board.mobility(Player::White), generic.mobility(Player::White));
+                for m in board.moves() {
+                    let (fast, generic) = (board.play(&m).unwrap(), generic.play(&m).unwrap());
+                    assert!(matches!((&fast.discs, &generic.discs), (Discs::Words { .. }, Discs::Bitmaps { .. })));
+                    assert_eq!(fast, generic);
+                }
+                let m = *board.moves().choose(&mut rng).unwrap();
+                board = board.play(&m).unwrap();
+            }
+        }
+        assert_eq!(Board::new(8).play(&Move::new(0, 0)), None);
+    }
+
+    #[test]
+    fn stable_discs() {
+        for s in (4..=10).filter(|&s| s % 2 == 0) {
+            let (black, white) = Board::new(s).stable_discs();
+            assert!(black.is_empty() && white.is_empty());
+
+            // A black top edge, with two white discs under it that can still be flipped.
+            let mut board = Board::new(s)
+                .set(s / 2 - 1, s / 2 - 1, Square::Empty)
+                .set(s / 2, s / 2 - 1, Square::Empty)
+                .set(s / 2 - 1, s / 2, Square::Empty)
+                .set(s / 2, s / 2, Square::Empty)
+                .set(1, 1, Square::Disc(Player::White))
+                .set(2, 1, Square::Disc(Player::White));
+            for x in 0..s {
+                board = board.set(x, 0, Square::Disc(Player::Black));
+            }
+            let (black, white) = board.stable_discs();
+            assert_eq!(black, board.bitmaps().0);
+            assert!(white.is_empty());
+
+            // A full bottom row of alternating colors is stable, a full row in the middle is not.
+            let mut board = Board::new(s)
+                .set(s / 2 - 1, s / 2 - 1, Square::Empty)
+                .set(s / 2, s / 2 - 1, Square::Empty);
+            for x in 0..s {
+                let player = if x % 2 == 0 { Player::Black } else { Player::White };
+                board = board
+                    .set(x, s - 1, Square::Disc(player))
+                    .set(x, s / 2, Square::Disc(player.other()));
+            }
+            let (black, white) = board.stable_discs();
+            assert_eq!(black.union(&white).popcount(), s as u32);
+            for x in 0..s {
+                assert!(black.get(x, s - 1) || white.get(x, s - 1));
+            }
+
+            // Corners, and edges filled from a corner.
+            let board = Board::new(s)
+                .set(0, 0, Square::Disc(Player::White))
+                .set(1, 0, Square::Disc(Player::White))
+                .set(2, 0, Square::Disc(Player::Black))
+                .set(s - 1, s - 1, Square::Disc(Player::Black))
+                .set(s - 1, s - 2, Square::Disc(Player::Black))
+                .set(s - 2, s - 2, Square::Disc(Player::Black));
+            let (black, white) = board.stable_discs();
+            assert_eq!(black, Bitmap::new(s).set(s - 1, s - 1).set(s - 1, s - 2));
+            assert_eq!(white, Bitmap::new(s).set(0, 0).set(1, 0));
+        }
+
+        // A board of one color with a single empty square is entirely stable.
+        let mut board = Board::new(8);
+        for x in 0..8 {
+            for y in 0..8 {
+                board = board.set(x, y, Square::Disc(Player::White));
+            }
+        }
+        let board = board.set(3, 3, Square::Empty);
+        let (black, white) = board.stable_discs();
+        assert!(black.is_empty());
+        assert_eq!(white.popcount(), 63);
+    }
+
     #[test]
     fn ignore_chars() {
         let mut iter = "BEFORE COMMENT #IN COMMENT\nAFTER COMMENT".chars();
@@ -547,6 +941,41 @@ mod tests {
         println!("{}", no_comment.into_iter().collect::<String>());
     }
 
+    #[test]
+    fn larger_boards() {
+        for (width, height) in [(12, 12), (14, 14), (8, 10), (10, 8), (4, 14), (6, 4), (9, 9), (5, 7), (13, 4)] {
+            let board = Board::rectangular(width, height);
+            assert_eq!((board.width(), board.height()), (width, height));
+            assert_eq!(board.score(), (2, 2));
+            assert_eq!(board.empty_count(), width * height - 4);
+            let (x, y) = ((width - 1) / 2, (height - 1) / 2);
+            assert_eq!(board.get(x, y), Square::Disc(Player::White));
+            assert_eq!(board.get(x + 1, y), Square::Disc(Player::Black));
+            assert_eq!(board.get(x, y + 1), Square::Disc(Player::Black));
+            assert_eq!(board.get(x + 1, y + 1), Square::Disc(Player::White));
+            assert_eq!(board.move_count(), 4);
+            assert_eq!(String::from(&board).parse(), Ok(board.clone()));
+
+            // The moves stay on the board until the end of the game.
+            let mut board = board;
+            while board.player.is_some() {
+                let moves = board.moves();
+                assert!(moves.iter().all(|m| m.x() < width && m.y() < height));
+                assert_eq!(String::from(&board).parse(), Ok(board.clone()));
+                board = board.play(moves.last().unwrap()).unwrap();
+            }
+            let (black, white) = board.score();
+            assert_eq!(black + white + board.empty_count(), width * height);
+        }
+        assert_eq!(Board::new(12), Board::rectangular(12, 12));
+        // On odd sides, the starting discs are closer to the top and left edges.
+        assert_eq!(Board::new(3).get(1, 1), Square::Disc(Player::White));
+        assert_eq!(Board::new(3).move_count(), 2);
+        assert_eq!(Board::rectangular(7, 4).get(3, 1), Square::Disc(Player::White));
+        assert_eq!(Board::rectangular(8, 10).play(&"D4".parse().unwrap()).unwrap().get(3, 4), Square::Disc(Player::Black));
+        Board::rectangular(14, 8).pretty_print();
+    }
+
     #[test]
     fn file_conversion() {
         let mut file: File = tempfile::tempfile().unwrap();
@@ -577,7 +1006,8 @@ mod tests {
         let mut file: File = tempfile::tempfile().unwrap();
         write!(file, "X\n____\n_OX_\n_XO_\n____\n____").unwrap();
         file.seek(SeekFrom::Start(0)).unwrap();
-        assert_eq!(Board::try_from(file), Err(ParsingError::InconsistentSize));
+        // Boards may have an odd number of rows.
+        assert_eq!(Board::try_from(file).map(|b| (b.width(), b.height())), Ok((4, 5)));
 
         let mut file: File = tempfile::tempfile().unwrap();
         write!(file, "X\n____\n_OX__\n_XO_\n____").unwrap();
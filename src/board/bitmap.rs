@@ -1,13 +1,58 @@
+use super::{MAX_SIZE, Transform};
+
 use std::{
+    cmp::Ordering,
     io::stdout,
     ops::{BitAnd, BitOr},
 };
 
-/// A structure representing a square grid of boolean cells.
-#[derive(Debug, Clone, PartialEq)]
+/// A structure representing a rectangular grid of boolean cells, of at most `MAX_SIZE` columns
+/// and rows.
+// The cell at `(x, y)` is the bit `x + y * (width + 1)` of a 256-bit number, stored as two words
+// with the low one first. The column between the rows is always empty after a `not`, so that
+// shifted cells do not wrap around from one row to the next.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Bitmap {
-    pub size: u8,
-    bm: u128
+    pub width: u8,
+    pub height: u8,
+    bm: [u128; 2],
+}
+
+// The full bitmaps of every shape, indexed by width then height.
+static FULL: [[[u128; 2]; MAX_SIZE as usize + 1]; MAX_SIZE as usize + 1] = full_bitmaps();
+
+const fn full_bitmaps() -> [[[u128; 2]; MAX_SIZE as usize + 1]; MAX_SIZE as usize + 1] {
+    let mut full = [[[0; 2]; MAX_SIZE as usize + 1]; MAX_SIZE as usize + 1];
+    let mut width = 0;
+    while width <= MAX_SIZE as usize {
+        let mut height = 0;
+        while height <= MAX_SIZE as usize {
+            let mut y = 0;
+            while y < height {
+                let mut x = 0;
+                while x < width {
+                    let i = x + y * (width + 1);
+                    full[width][height][i / 128] |= 1 << (i % 128);
+                    x += 1;
+                }
+                y += 1;
+            }
+            height += 1;
+        }
+        width += 1;
+    }
+    full
+}
+
+// Shift the 256-bit number by less than a word, as all the shifts of cells are.
+fn shl([low, high]: [u128; 2], by: u8) -> [u128; 2] {
+    debug_assert!(0 < by && by < 128);
+    [low << by, high << by | low >> (128 - by)]
+}
+
+fn shr([low, high]: [u128; 2], by: u8) -> [u128; 2] {
+    debug_assert!(0 < by && by < 128);
+    [low >> by | high << (128 - by), high >> by]
 }
 
 impl Bitmap {
@@ -16,173 +61,193 @@ impl Bitmap {
         Self::empty(size)
     }
 
-    /// Create an empty `Bitmap` of size at most 10.
+    /// Create an empty square `Bitmap`.
     pub fn empty(size: u8) -> Self {
-        if size > 10 {
-            panic!("Bitmap too big")
-        }
-        Self { size, bm: 0 }
+        Self::empty_rect(size, size)
     }
 
-    /// Create a full `Bitmap` of size at most 10.
+    /// Create a full square `Bitmap`.
     pub fn full(size: u8) -> Self {
-        if size > 10 {
+        Self::full_rect(size, size)
+    }
+
+    /// Create an empty `Bitmap` with the given numbers of columns and rows.
+    pub fn empty_rect(width: u8, height: u8) -> Self {
+        if width > MAX_SIZE || height > MAX_SIZE {
             panic!("Bitmap too big")
         }
-        if size % 2 != 0 {
-            panic!("Bitmap should be evenly sized");
-        }
-        Self {
-            size,
-            bm: [0, 0, 0x1b, 0, 0x7bdef, 0, 0x1fbf7efdfbf, 0, 0x7fbfdfeff7fbfdfeff, 0, 0x1ffbff7feffdffbff7feffdffbff][size as usize]
+        Self { width, height, bm: [0; 2] }
+    }
+
+    /// Create a full `Bitmap` with the given numbers of columns and rows.
+    pub fn full_rect(width: u8, height: u8) -> Self {
+        if width > MAX_SIZE || height > MAX_SIZE {
+            panic!("Bitmap too big")
         }
+        Self { width, height, bm: FULL[width as usize][height as usize] }
     }
 
     /// Negate the `Bitmap`.
     pub fn not(&self) -> Self {
-        Self {
-            size: self.size,
-            bm: !self.bm & Self::full(self.size).bm
-        }
+        let full = FULL[self.width as usize][self.height as usize];
+        self.with([!self.bm[0] & full[0], !self.bm[1] & full[1]])
     }
 
     /// Return whether the `Bitmap` is empty.
     pub fn is_empty(&self) -> bool {
-        self.bm == 0
+        self.bm == [0; 2]
     }
 
     /// Return whether the `Bitmap` is not empty.
     pub fn not_empty(&self) -> bool {
-        self.bm != 0
+        !self.is_empty()
     }
 
     /// Set the given cell of the `Bitmap`.
     pub fn set(&self, x: u8, y: u8) -> Self {
-        assert!(x < self.size && y < self.size);
-        Self {
-            size: self.size,
-            bm: self.bm | (1_u128 << (x + y * (self.size + 1)))
-        }
+        let (word, bit) = self.index(x, y);
+        let mut bm = self.bm;
+        bm[word] |= bit;
+        self.with(bm)
     }
 
     /// Unset the given cell of the `Bitmap`.
     pub fn unset(&self, x: u8, y: u8) -> Self {
-        assert!(x < self.size && y < self.size);
-        Self {
-            size: self.size,
-            bm: self.bm & !(1_u128 << (x + y * (self.size + 1)))
-        }
+        let (word, bit) = self.index(x, y);
+        let mut bm = self.bm;
+        bm[word] &= !bit;
+        self.with(bm)
     }
 
     /// Get the value of the given cell of the `Bitmap`.
     pub fn get(&self, x: u8, y: u8) -> bool {
-        assert!(x < self.size && y < self.size);
-        Self::new(self.size).set(x, y).intersection(self).bm != 0
+        let (word, bit) = self.index(x, y);
+        self.bm[word] & bit != 0
     }
 
     /// Return the number of cells st in the `Bitmap`.
     pub fn popcount(&self) -> u32 {
-        self.bm.count_ones()
+        self.bm[0].count_ones() + self.bm[1].count_ones()
     }
 
     /// Shift the cells of the `Bitmap` north.
     pub fn shift_north(&self) -> Self {
-        Self {
-            size: self.size,
-            bm: self.bm >> (self.size + 1)
-        }
+        self.with(shr(self.bm, self.width + 1))
     }
 
     /// Shift the cells of the `Bitmap` south.
     pub fn shift_south(&self) -> Self {
-        Self {
-            size: self.size,
-            bm: self.bm << (self.size + 1)
-        }
+        self.with(shl(self.bm, self.width + 1))
     }
 
     /// Shift the cells of the `Bitmap` east.
     pub fn shift_east(&self) -> Self {
-        Self {
-            size: self.size,
-            bm: self.bm << 1
-        }
+        self.with(shl(self.bm, 1))
     }
 
     /// Shift the cells of the `Bitmap` west.
     pub fn shift_west(&self) -> Self {
-        Self {
-            size: self.size,
-            bm: self.bm >> 1
-        }
+        self.with(shr(self.bm, 1))
     }
 
     /// Shift the cells of the `Bitmap` north-east.
     pub fn shift_ne(&self) -> Self {
-        Self {
-            size: self.size,
-            bm: self.bm >> self.size
-        }
+        self.with(shr(self.bm, self.width))
     }
 
     /// Shift the cells of the `Bitmap` south-east.
     pub fn shift_se(&self) -> Self {
-        Self {
-            size: self.size,
-            bm: self.bm << (self.size + 2)
-        }
+        self.with(shl(self.bm, self.width + 2))
     }
 
     /// Shift the cells of the `Bitmap` south-west.
     pub fn shift_sw(&self) -> Self {
-        Self {
-            size: self.size,
-            bm: self.bm << self.size
-        }
+        self.with(shl(self.bm, self.width))
     }
 
     /// Shift the cells of the `Bitmap` north-west.
     pub fn shift_nw(&self) -> Self {
+        self.with(shr(self.bm, self.width + 2))
+    }
+
+    /// Return the cells of an 8x8 `Bitmap` as a 64-bit word, the cell at `(x, y)` being the
+    /// bit `x + 8 * y`.
+    pub fn to_u64(&self) -> u64 {
+        assert_eq!((self.width, self.height), (8, 8));
+        (0..8).fold(0, |bits, y| bits | ((self.bm[0] >> (9 * y)) as u64 & 0xff) << (8 * y))
+    }
+
+    /// Create an 8x8 `Bitmap` from a 64-bit word, as returned by `to_u64`.
+    pub fn from_u64(bits: u64) -> Self {
         Self {
-            size: self.size,
-            bm: self.bm >> (self.size + 2)
+            width: 8,
+            height: 8,
+            bm: [(0..8).fold(0, |bm, y| bm | ((bits >> (8 * y)) as u128 & 0xff) << (9 * y)), 0],
+        }
+    }
+
+    /// Map the cells of the `Bitmap` by one of the symmetries of the rectangle, or by a rotation
+    /// or transposition that swaps its numbers of columns and rows.
+    pub fn transform(&self, transform: Transform) -> Self {
+        let mut image = match transform.transposes() {
+            false => Self::empty_rect(self.width, self.height),
+            true => Self::empty_rect(self.height, self.width),
+        };
+        for (x, y) in self.clone() {
+            let (x, y) = transform.apply(self.width, self.height, x, y);
+            image = image.set(x, y);
         }
+        image
+    }
+
+    /// Rotate the `Bitmap` a quarter turn clockwise.
+    pub fn rotate(&self) -> Self {
+        self.transform(Transform::RotateQuarter)
+    }
+
+    /// Reflect the `Bitmap` left to right.
+    pub fn flip_horizontal(&self) -> Self {
+        self.transform(Transform::FlipHorizontal)
+    }
+
+    /// Reflect the `Bitmap` top to bottom.
+    pub fn flip_vertical(&self) -> Self {
+        self.transform(Transform::FlipVertical)
+    }
+
+    /// Reflect the `Bitmap` along its diagonal from the top left cell to the bottom right one.
+    pub fn transpose(&self) -> Self {
+        self.transform(Transform::Transpose)
     }
 
     /// Compute the interstion with another `Bitmap`.
     pub fn intersection(&self, other: &Self) -> Self {
-        assert_eq!(self.size, other.size);
-        Self {
-            size: self.size,
-            bm: self.bm & other.bm
-        }
+        self.assert_same_shape(other);
+        self.with([self.bm[0] & other.bm[0], self.bm[1] & other.bm[1]])
     }
 
     /// Compute the union with another `Bitmap`.
     pub fn union(&self, other: &Self) -> Self {
-        assert_eq!(self.size, other.size);
-        Self {
-            size: self.size,
-            bm: self.bm | other.bm
-        }
+        self.assert_same_shape(other);
+        self.with([self.bm[0] | other.bm[0], self.bm[1] | other.bm[1]])
     }
 
     /// Compute the set difference with another `Bitmap`.
     pub fn setminus(&self, other: &Self) -> Self {
-        assert_eq!(self.size, other.size);
+        self.assert_same_shape(other);
         self.intersection(&other.not())
     }
 
     /// Return whether the `Bitmap` is a subset of another.
     pub fn subset_of(&self, other: &Self) -> bool {
-        assert_eq!(self.size, other.size);
+        self.assert_same_shape(other);
         self.union(other) == *other
     }
 
     /// Return whether the `Bitmap` is a superset of another.
     #[allow(dead_code)]
     pub fn superset_of(&self, other: &Self) -> bool {
-        assert_eq!(self.size, other.size);
+        self.assert_same_shape(other);
         self.intersection(other) == *other
     }
 
@@ -190,8 +255,8 @@ impl Bitmap {
     #[allow(dead_code)]
     pub fn print(&self) {
         let handle = stdout().lock();
-        for y in 0..self.size {
-            for x in 0..self.size {
+        for y in 0..self.height {
+            for x in 0..self.width {
                 print!("{}", if self.get(x, y) { '*' } else { '_' });
             }
             println!();
@@ -201,12 +266,41 @@ impl Bitmap {
 
     /// Return the first cell that is set, if any are set.
     fn lowest(&self) -> Option<(u8, u8)> {
-        if self.is_empty() {
-            None
-        } else {
-            let i: u8 = self.bm.trailing_zeros().try_into().expect("u8 shouldn't have more than 255 zeros");
-            Some((i % (self.size + 1), i / (self.size + 1)))
-        }
+        let i = match self.bm {
+            [0, 0] => return None,
+            [0, high] => 128 + high.trailing_zeros(),
+            [low, _] => low.trailing_zeros(),
+        };
+        let row = self.width as u32 + 1;
+        Some(((i % row) as u8, (i / row) as u8))
+    }
+
+    // Return the word of the given cell, and its bit in the word.
+    fn index(&self, x: u8, y: u8) -> (usize, u128) {
+        assert!(x < self.width && y < self.height);
+        let i = x as usize + y as usize * (self.width as usize + 1);
+        (i / 128, 1 << (i % 128))
+    }
+
+    fn with(&self, bm: [u128; 2]) -> Self {
+        Self { width: self.width, height: self.height, bm }
+    }
+
+    fn assert_same_shape(&self, other: &Self) {
+        assert_eq!((self.width, self.height), (other.width, other.height));
+    }
+}
+
+// `Bitmap`s of the same shape are ordered like their cells read row by row, from the last one.
+impl Ord for Bitmap {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.width, self.height, self.bm[1], self.bm[0]).cmp(&(other.width, other.height, other.bm[1], other.bm[0]))
+    }
+}
+
+impl PartialOrd for Bitmap {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
@@ -214,21 +308,27 @@ impl Iterator for Bitmap {
     type Item = (u8, u8);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.lowest().map(|(x, y)| {
-            *self = self.unset(x, y);
-            (x, y)
-        })
+        let cell = self.lowest()?;
+        // Clear the lowest bit set.
+        match &mut self.bm {
+            [0, high] => *high &= *high - 1,
+            [low, _] => *low &= *low - 1,
+        }
+        Some(cell)
     }
 }
 
 impl From<Vec<Vec<bool>>> for Bitmap {
+    // The rows must all have the same length.
     fn from(val: Vec<Vec<bool>>) -> Self {
-        let mut bm = Bitmap::empty(val.len().try_into().expect("bitmaps must be at most of size 10"));
+        let height = val.len().try_into().expect("bitmaps must be at most of size MAX_SIZE");
+        let width = val.first().map_or(0, |r| r.len()).try_into().expect("bitmaps must be at most of size MAX_SIZE");
+        let mut bm = Bitmap::empty_rect(width, height);
         for (y, r) in val.into_iter().enumerate() {
-            let y = y.try_into().expect("bitmaps must be at most of size 10");
+            let y = y.try_into().expect("bitmaps must be at most of size MAX_SIZE");
             for (x, b) in r.into_iter().enumerate() {
                 if b {
-                    bm = bm.set(x.try_into().expect("bitmaps must be at most of size 10"), y);
+                    bm = bm.set(x.try_into().expect("bitmaps must be at most of size MAX_SIZE"), y);
                 }
             }
         }
@@ -258,7 +358,7 @@ mod tests {
 
     #[test]
     fn shifting() {
-        for s in (4..=10).filter(|&s| s % 2 == 0) {
+        for s in (4..=MAX_SIZE).filter(|&s| s % 2 == 0) {
             let bitmap = Bitmap::new(s).set(1, 1);
             assert_eq!(bitmap.shift_north(), Bitmap::new(s).set(1, 0));
             assert_eq!(bitmap.shift_south(), Bitmap::new(s).set(1, 2));
@@ -293,9 +393,18 @@ mod tests {
         assert_eq!(bitmap.popcount(), 2);
     }
 
+    #[test]
+    fn words() {
+        let bitmap = Bitmap::new(8).set(0, 0).set(7, 0).set(3, 4).set(7, 7);
+        assert_eq!(bitmap.to_u64(), 1 | 1 << 7 | 1 << 35 | 1 << 63);
+        assert_eq!(Bitmap::from_u64(bitmap.to_u64()), bitmap);
+        assert_eq!(Bitmap::full(8).to_u64(), u64::MAX);
+        assert_eq!(Bitmap::from_u64(u64::MAX), Bitmap::full(8));
+    }
+
     #[test]
     fn full() {
-        for s in (2..=10).filter(|&s| s % 2 == 0) {
+        for s in (2..=MAX_SIZE).filter(|&s| s % 2 == 0) {
             let full = Bitmap::full(s);
             for x in 0..s {
                 for y in 0..s {
@@ -307,7 +416,7 @@ mod tests {
 
     #[test]
     fn not() {
-        for s in (2..=10).filter(|&s| s % 2 == 0) {
+        for s in (2..=MAX_SIZE).filter(|&s| s % 2 == 0) {
             let not_full = Bitmap::full(s).not();
             for x in 0..s {
                 for y in 0..s {
@@ -319,7 +428,7 @@ mod tests {
 
     #[test]
     fn is_empty() {
-        for s in (2..=10).filter(|&s| s % 2 == 0) {
+        for s in (2..=MAX_SIZE).filter(|&s| s % 2 == 0) {
             assert!(Bitmap::new(s).is_empty());
             assert!(Bitmap::full(s).not().is_empty());
         }
@@ -338,4 +447,22 @@ mod tests {
             vec!((1, 0), (2, 0), (0, 1), (1, 1), (0, 2))
         );
     }
+
+    #[test]
+    fn rectangles() {
+        let full = Bitmap::full_rect(8, 14);
+        assert_eq!(full.popcount(), 8 * 14);
+        assert!(full.not().is_empty());
+        assert_eq!(full.clone().count(), 8 * 14);
+        assert_eq!(full.clone().last(), Some((7, 13)));
+        assert_eq!(Bitmap::full_rect(14, 8).transpose(), full);
+
+        // Cells move between the two words of the largest bitmaps.
+        let bitmap = Bitmap::new(14).set(7, 8).set(13, 12);
+        assert_eq!(bitmap.shift_south(), Bitmap::new(14).set(7, 9).set(13, 13));
+        assert_eq!(bitmap.shift_north(), Bitmap::new(14).set(7, 7).set(13, 11));
+        assert_eq!(bitmap.shift_se().shift_nw(), bitmap);
+        assert_eq!(bitmap.shift_west(), Bitmap::new(14).set(6, 8).set(12, 12));
+        assert_eq!(bitmap.collect::<Vec<(u8, u8)>>(), vec!((7, 8), (13, 12)));
+    }
 }
@@ -0,0 +1,97 @@
+// Move generation for 8x8 boards on 64-bit words, the square at column `x` and row `y` being the
+// bit `x + 8 * y`.
+// Lines of discs are followed in all 8 directions at once with Kogge-Stone fills, which take
+// 3 steps to cover the 7 squares a line can have, without branches.
+// 8x8 boards keep their discs in these words.
+
+const NOT_A: u64 = 0xfefe_fefe_fefe_fefe;
+const NOT_H: u64 = 0x7f7f_7f7f_7f7f_7f7f;
+
+// The shift of each direction, positive towards the bottom right, and the squares a shift in that
+// direction may land on without wrapping around the board.
+const DIRECTIONS: [(i8, u64); 8] = [
+    (1, NOT_A), (-1, NOT_H), (8, !0), (-8, !0),
+    (9, NOT_A), (-9, NOT_H), (7, NOT_H), (-7, NOT_A),
+];
+
+fn shift(bits: u64, by: i8) -> u64 {
+    if by > 0 { bits << by } else { bits >> -by }
+}
+
+// Return `from` and the discs of `through` that follow it without a gap in the direction.
+fn fill(from: u64, through: u64, (by, mask): (i8, u64)) -> u64 {
+    let mut generator = from;
+    let mut propagator = through & mask;
+    generator |= propagator & shift(generator, by);
+    propagator &= shift(propagator, by);
+    generator |= propagator & shift(generator, 2 * by);
+    propagator &= shift(propagator, 2 * by);
+    generator | (propagator & shift(generator, 4 * by))
+}
+
+/// Return the moves of the player.
+pub fn moves(player: u64, opponent: u64) -> u64 {
+    let empty = !(player | opponent);
+    DIRECTIONS.iter().fold(0, |moves, &(by, mask)| {
+        let line = fill(player, opponent, (by, mask)) & opponent;
+        moves | (shift(line, by) & mask & empty)
+    })
+}
+
+/// Return the discs of the opponent flipped by the player's move on the given square.
+pub fn flips(player: u64, opponent: u64, square: u32) -> u64 {
+    let m = 1 << square;
+    DIRECTIONS.iter().fold(0, |flips, &(by, mask)| {
+        let line = fill(m, opponent, (by, mask)) & opponent;
+        // The line is flipped if it ends on a disc of the player.
+        let closed = shift(line | m, by) & mask & !line & player != 0;
+        flips | (line & 0u64.wrapping_sub(closed as u64))
+    })
+}
+
+/// Return the columns and rows of the squares of the word, in the order of their bits.
+pub fn squares(mut bits: u64) -> impl Iterator<Item = (u8, u8)> {
+    std::iter::from_fn(move || {
+        if bits == 0 {
+            return None;
+        }
+        let square = bits.trailing_zeros() as u8;
+        bits &= bits - 1;
+        Some((square % 8, square / 8))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: u32, y: u32) -> u64 {
+        1 << (x + 8 * y)
+    }
+
+    #[test]
+    fn starting_position() {
+        let black = square(4, 3) | square(3, 4);
+        let white = square(3, 3) | square(4, 4);
+        assert_eq!(moves(black, white), square(3, 2) | square(2, 3) | square(5, 4) | square(4, 5));
+        assert_eq!(flips(black, white, 3 + 8 * 2), square(3, 3));
+        assert_eq!(flips(black, white, 0), 0);
+    }
+
+    #[test]
+    fn edges() {
+        // Lines do not wrap from one edge to the other.
+        let player = square(7, 0);
+        let opponent = square(0, 1) | square(1, 1);
+        assert_eq!(moves(player, opponent), 0);
+        let player = square(0, 0);
+        let opponent = (1..7).fold(0, |bits, x| bits | square(x, 0));
+        assert_eq!(moves(player, opponent), square(7, 0));
+        assert_eq!(flips(player, opponent, 7), opponent);
+        // A line that reaches the edge without a disc of the player is not flipped.
+        assert_eq!(flips(0, opponent, 7), 0);
+        let player = square(0, 7);
+        let opponent = square(1, 6) | square(2, 5) | square(3, 4) | square(4, 3) | square(5, 2) | square(6, 1);
+        assert_eq!(flips(player, opponent, 7), opponent);
+    }
+}
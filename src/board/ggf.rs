@@ -0,0 +1,129 @@
+use super::{is_valid_size, Board, ParsingError, Player, Square};
+
+// In the GGF `BO` field, a board is written as its size, its rows from top to bottom, and the
+// player to move, e.g. `4 ---- -O*- -*O- ---- *`.
+// The size of a rectangular board is written as its numbers of columns and rows, e.g. `8x10`.
+// Black discs are `*`, white discs `O`, and empty squares `-`.
+// Whitespace between the squares is ignored.
+impl Board {
+    /// Parse a board from the value of a GGF `BO` field.
+    pub fn from_ggf(s: &str) -> Result<Self, ParsingError> {
+        let s = s.trim_start();
+        let (size, squares) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        if size.is_empty() {
+            return Err(ParsingError::EmptyFile);
+        }
+        let Some((width, height)) = parse_size(size) else {
+            return Err(ParsingError::BadSize);
+        };
+        let (width, height) = (width as usize, height as usize);
+
+        let mut squares: Vec<char> = squares.chars().filter(|c| !c.is_whitespace()).collect();
+        if squares.len() != width * height + 1 {
+            return Err(ParsingError::InconsistentSize);
+        }
+        let player = match squares.pop().expect("there is at least one square") {
+            '*' => Player::Black,
+            'O' => Player::White,
+            c => return Err(ParsingError::PlayerParseError(c)),
+        };
+
+        let mut grid: Vec<Vec<Square>> = vec!();
+        for row in squares.chunks(width) {
+            grid.push(row.iter().map(|c| match c {
+                '*' => Ok(Square::Disc(Player::Black)),
+                'O' => Ok(Square::Disc(Player::White)),
+                '-' => Ok(Square::Empty),
+                c => Err(ParsingError::InvalidCharacter(*c)),
+            }).collect::<Result<_, _>>()?);
+        }
+
+        Ok(Self::from_grid(&grid, player))
+    }
+
+    /// Return the value of the GGF `BO` field for this board.
+    /// A finished game is written with `Black` to move.
+    pub fn to_ggf(&self) -> String {
+        let mut out = self.ggf_size();
+        for y in 0..self.height {
+            out.push(' ');
+            for x in 0..self.width {
+                out.push(match self.get(x, y) {
+                    Square::Disc(Player::Black) => '*',
+                    Square::Disc(Player::White) => 'O',
+                    Square::Empty => '-',
+                });
+            }
+        }
+        out.push(' ');
+        out.push(match self.player {
+            Some(Player::White) => 'O',
+            _ => '*',
+        });
+        out
+    }
+
+    /// Return the size of the board as written in GGF fields, e.g. `8` or `8x10`.
+    pub fn ggf_size(&self) -> String {
+        match self.width == self.height {
+            true => self.width.to_string(),
+            false => format!("{}x{}", self.width, self.height),
+        }
+    }
+}
+
+/// Parse the size of a board as written by `Board::ggf_size` into its numbers of columns and rows,
+/// if they are valid.
+pub fn parse_size(s: &str) -> Option<(u8, u8)> {
+    let (width, height) = match s.split_once('x') {
+        Some((width, height)) => (width.parse().ok()?, height.parse().ok()?),
+        None => {
+            let size = s.parse().ok()?;
+            (size, size)
+        },
+    };
+    (is_valid_size(width) && is_valid_size(height)).then_some((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let start = Board::new(8);
+        assert_eq!(start.to_ggf(), "8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *");
+        assert_eq!(Board::from_ggf(&start.to_ggf()), Ok(start.clone()));
+
+        let board = start.play(&start.moves()[0]).unwrap();
+        assert!(board.to_ggf().ends_with(" O"));
+        assert_eq!(Board::from_ggf(&board.to_ggf()), Ok(board));
+
+        for size in [2, 3, 4, 6, 9, 10, 12, 13, 14] {
+            assert_eq!(Board::from_ggf(&Board::new(size).to_ggf()), Ok(Board::new(size)));
+        }
+
+        let board = Board::rectangular(4, 6);
+        assert_eq!(board.to_ggf(), "4x6 ---- ---- -O*- -*O- ---- ---- *");
+        assert_eq!(Board::from_ggf(&board.to_ggf()), Ok(board));
+    }
+
+    #[test]
+    fn whitespace() {
+        assert_eq!(Board::from_ggf("  4\n----\n-O*-\n-*O-\n----\n*"), Ok(Board::new(4)));
+        assert_eq!(Board::from_ggf("4 ---- -O *- -*O- ---- *"), Ok(Board::new(4)));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Board::from_ggf(""), Err(ParsingError::EmptyFile));
+        assert_eq!(Board::from_ggf("four ---- -O*- -*O- ---- *"), Err(ParsingError::BadSize));
+        assert_eq!(Board::from_ggf("1 - *"), Err(ParsingError::BadSize));
+        assert_eq!(Board::from_ggf("16 *"), Err(ParsingError::BadSize));
+        assert_eq!(Board::from_ggf("4x1 ---- *"), Err(ParsingError::BadSize));
+        assert_eq!(Board::from_ggf("4x *"), Err(ParsingError::BadSize));
+        assert_eq!(Board::from_ggf("4 ---- -O*- -*O- *"), Err(ParsingError::InconsistentSize));
+        assert_eq!(Board::from_ggf("4 ---- -O*- -*O- ---- -"), Err(ParsingError::PlayerParseError('-')));
+        assert_eq!(Board::from_ggf("4 ---- -OX- -*O- ---- *"), Err(ParsingError::InvalidCharacter('X')));
+    }
+}
@@ -0,0 +1,231 @@
+use super::{Board, Move};
+
+/// One of the 8 symmetries of a square board, mapping each square to another.
+/// Rotations are clockwise, and transpositions reflect the board along its main diagonal, from the
+/// top left corner to the bottom right one, or along the other diagonal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    Identity,
+    RotateQuarter,
+    RotateHalf,
+    RotateThreeQuarters,
+    FlipHorizontal,
+    FlipVertical,
+    Transpose,
+    AntiTranspose,
+}
+
+impl Transform {
+    /// All the transforms, starting with the identity.
+    pub const ALL: [Self; 8] = [
+        Self::Identity,
+        Self::RotateQuarter,
+        Self::RotateHalf,
+        Self::RotateThreeQuarters,
+        Self::FlipHorizontal,
+        Self::FlipVertical,
+        Self::Transpose,
+        Self::AntiTranspose,
+    ];
+
+    /// Return the transform undoing this one.
+    pub fn inverse(&self) -> Self {
+        match self {
+            Self::RotateQuarter => Self::RotateThreeQuarters,
+            Self::RotateThreeQuarters => Self::RotateQuarter,
+            t => *t,
+        }
+    }
+
+    /// Return whether the transform swaps the columns and the rows, so that it maps a board to
+    /// one with as many columns as it has rows.
+    pub fn transposes(&self) -> bool {
+        matches!(self, Self::RotateQuarter | Self::RotateThreeQuarters | Self::Transpose | Self::AntiTranspose)
+    }
+
+    /// Return the coordinates the square at the given coordinates is mapped to, on a board with the
+    /// given numbers of columns and rows.
+    pub fn apply(&self, width: u8, height: u8, x: u8, y: u8) -> (u8, u8) {
+        assert!(x < width && y < height);
+        let (right, bottom) = (width - 1, height - 1);
+        match self {
+            Self::Identity => (x, y),
+            Self::RotateQuarter => (bottom - y, x),
+            Self::RotateHalf => (right - x, bottom - y),
+            Self::RotateThreeQuarters => (y, right - x),
+            Self::FlipHorizontal => (right - x, y),
+            Self::FlipVertical => (x, bottom - y),
+            Self::Transpose => (y, x),
+            Self::AntiTranspose => (bottom - y, right - x),
+        }
+    }
+}
+
+impl Move {
+    /// Return the move mapped by the transform on a board with the given numbers of columns and
+    /// rows.
+    pub fn transform(&self, width: u8, height: u8, transform: Transform) -> Self {
+        let (x, y) = transform.apply(width, height, self.x, self.y);
+        Self::new(x, y)
+    }
+}
+
+impl Board {
+    /// Return the board mapped by the transform, with the same player to move.
+    /// The columns and rows of a rectangular board are swapped by the transforms that transpose.
+    pub fn transform(&self, transform: Transform) -> Self {
+        let (black, white) = self.bitmaps();
+        Self::from_bitmaps(black.transform(transform), white.transform(transform), self.move_bitmap().transform(transform), self.player)
+    }
+
+    /// Return the smallest of the symmetric versions of the board, comparing the discs of black
+    /// then those of white, and the transform mapping the board to it.
+    /// A square board has 8 symmetric versions, and a rectangular one 4, as the transforms that
+    /// transpose change its shape.
+    /// Symmetric boards have the same canonical board. When several transforms give it, which
+    /// happens for boards that are symmetric themselves, the first of `Transform::ALL` is returned.
+    pub fn canonical(&self) -> (Self, Transform) {
+        self.symmetries()
+            .map(|t| (self.transform(t), t))
+            .min_by(|(a, _), (b, _)| a.bitmaps().cmp(&b.bitmaps()))
+            .expect("the identity keeps the shape")
+    }
+
+    /// Return the transforms mapping the board to its canonical board: only one for most
+    /// boards, but several for the boards that are symmetric themselves.
+    pub fn canonical_transforms(&self) -> Vec<Transform> {
+        let (canonical, _) = self.canonical();
+        self.symmetries().filter(|&t| self.transform(t) == canonical).collect()
+    }
+
+    // Return the transforms that keep the shape of the board.
+    fn symmetries(&self) -> impl Iterator<Item = Transform> {
+        let square = self.width == self.height;
+        Transform::ALL.into_iter().filter(move |t| square || !t.transposes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::bitmap::Bitmap;
+
+    const SIZES: [u8; 7] = [2, 4, 6, 8, 10, 12, 14];
+
+    #[test]
+    fn squares() {
+        // The top left corner goes around the board.
+        let corners: Vec<(u8, u8)> = Transform::ALL.iter().map(|t| t.apply(8, 8, 0, 0)).collect();
+        assert_eq!(corners, vec!((0, 0), (7, 0), (7, 7), (0, 7), (7, 0), (0, 7), (0, 0), (7, 7)));
+        assert_eq!(Transform::RotateQuarter.apply(8, 8, 2, 0), (7, 2));
+        assert_eq!(Transform::Transpose.apply(8, 8, 2, 0), (0, 2));
+        assert_eq!(Transform::AntiTranspose.apply(8, 8, 2, 0), (7, 5));
+
+        for size in SIZES {
+            for t in Transform::ALL {
+                let mut image = vec!();
+                for y in 0..size {
+                    for x in 0..size {
+                        let (tx, ty) = t.apply(size, size, x, y);
+                        assert_eq!(t.inverse().apply(size, size, tx, ty), (x, y));
+                        image.push((tx, ty));
+                    }
+                }
+                // Every transform is a permutation of the squares.
+                image.sort();
+                image.dedup();
+                assert_eq!(image.len(), size as usize * size as usize);
+            }
+        }
+    }
+
+    #[test]
+    fn bitmaps() {
+        for size in SIZES {
+            let bitmap = Bitmap::new(size).set(0, 0).set(1, 0).set(size - 1, size - 1);
+            for t in Transform::ALL {
+                let image = bitmap.transform(t);
+                assert_eq!(image.popcount(), 3);
+                assert!(image.get(t.apply(size, size, 0, 0).0, t.apply(size, size, 0, 0).1));
+                assert!(image.get(t.apply(size, size, 1, 0).0, t.apply(size, size, 1, 0).1));
+                assert_eq!(image.transform(t.inverse()), bitmap);
+            }
+            assert_eq!(bitmap.rotate().rotate().rotate().rotate(), bitmap);
+            assert_eq!(bitmap.rotate(), bitmap.transform(Transform::RotateQuarter));
+            assert_eq!(bitmap.flip_horizontal(), bitmap.transform(Transform::FlipHorizontal));
+            assert_eq!(bitmap.flip_vertical(), bitmap.transform(Transform::FlipVertical));
+            assert_eq!(bitmap.transpose(), bitmap.transform(Transform::Transpose));
+            assert_eq!(bitmap.transpose().rotate(), bitmap.flip_horizontal());
+            assert_eq!(Bitmap::full(size).transform(Transform::AntiTranspose), Bitmap::full(size));
+        }
+    }
+
+    #[test]
+    fn starting_positions() {
+        for size in SIZES {
+            let start = Board::new(size);
+            let (canonical, transform) = start.canonical();
+            assert_eq!(start.transform(transform), canonical);
+            for t in Transform::ALL {
+                let board = start.transform(t);
+                assert_eq!(board.canonical().0, canonical, "size {}, {:?}", size, t);
+                assert_eq!(board.score(), start.score());
+                assert_eq!(board.move_count(), start.move_count());
+            }
+            // The starting position is symmetric along both diagonals and by a half turn.
+            assert_eq!(start.canonical_transforms().len(), 4);
+        }
+    }
+
+    #[test]
+    fn moves() {
+        let board = Board::new(8).play(&"F5".parse().unwrap()).unwrap();
+        assert_eq!(board.canonical_transforms().len(), 1);
+        for t in Transform::ALL {
+            let image = board.transform(t);
+            let mut moves: Vec<Move> = board.moves().iter().map(|m| m.transform(8, 8, t)).collect();
+            let mut expected = image.moves();
+            moves.sort_by_key(|m| (m.y(), m.x()));
+            expected.sort_by_key(|m| (m.y(), m.x()));
+            assert_eq!(moves, expected);
+
+            // Playing commutes with the transforms.
+            for m in board.moves() {
+                assert_eq!(board.play(&m).unwrap().transform(t), image.play(&m.transform(8, 8, t)).unwrap());
+                assert_eq!(m.transform(8, 8, t).transform(8, 8, t.inverse()), m);
+            }
+            assert_eq!(image.hash() == board.hash(), t == Transform::Identity);
+        }
+    }
+
+    #[test]
+    fn rectangles() {
+        // The transforms that transpose swap the dimensions of the board.
+        assert_eq!(Transform::RotateQuarter.apply(8, 6, 0, 0), (5, 0));
+        assert_eq!(Transform::AntiTranspose.apply(8, 6, 7, 0), (5, 0));
+        let bitmap = Bitmap::empty_rect(8, 6).set(0, 0).set(7, 1);
+        for t in Transform::ALL {
+            let image = bitmap.transform(t);
+            assert_eq!((image.width, image.height), if t.transposes() { (6, 8) } else { (8, 6) });
+            let (x, y) = t.apply(8, 6, 7, 1);
+            assert!(image.get(x, y));
+            assert_eq!(image.transform(t.inverse()), bitmap);
+        }
+
+        // A rectangular board only has the 4 symmetries that keep its shape.
+        let start = Board::rectangular(8, 10);
+        assert_eq!(start.canonical_transforms().len(), 2);
+        let board = start.play(&start.moves()[0]).unwrap();
+        let (canonical, transform) = board.canonical();
+        assert_eq!((canonical.width(), canonical.height()), (8, 10));
+        assert!(!transform.transposes());
+        let image = board.transform(Transform::RotateQuarter);
+        assert_eq!((image.width(), image.height()), (10, 8));
+        assert_eq!(image.score(), board.score());
+        assert_eq!(image.move_count(), board.move_count());
+        for m in board.moves() {
+            let played = board.play(&m).unwrap().transform(Transform::RotateQuarter);
+            assert_eq!(played, image.play(&m.transform(8, 10, Transform::RotateQuarter)).unwrap());
+        }
+    }
+}
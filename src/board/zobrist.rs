@@ -0,0 +1,114 @@
+use super::{Board, Discs, Player, fast};
+
+// Squares are indexed as `x + y * MAX_SIZE` so that the same key never describes two different
+// squares, whatever the shape of the board.
+const MAX_SIZE: usize = super::MAX_SIZE as usize;
+const SQUARES: usize = MAX_SIZE * MAX_SIZE;
+
+// One key per square and per player, then one key per number of columns and one per number of
+// rows, then the keys of the current player.
+const KEY_COUNT: usize = 2 * SQUARES + 2 * (MAX_SIZE + 1) + 2;
+const KEYS: [u64; KEY_COUNT] = keys();
+
+const BLACK_OFFSET: usize = 0;
+const WHITE_OFFSET: usize = SQUARES;
+const WIDTH_OFFSET: usize = 2 * SQUARES;
+const HEIGHT_OFFSET: usize = WIDTH_OFFSET + MAX_SIZE + 1;
+const WHITE_TO_MOVE: usize = HEIGHT_OFFSET + MAX_SIZE + 1;
+const GAME_OVER: usize = WHITE_TO_MOVE + 1;
+
+// Generate pseudo-random keys with the SplitMix64 generator, so that they are the same from one
+// run to the next.
+const fn keys() -> [u64; KEY_COUNT] {
+    let mut keys = [0; KEY_COUNT];
+    let mut state: u64 = 0x5265_7665_7273_6921;
+    let mut i = 0;
+    while i < KEY_COUNT {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+impl Board {
+    /// Return the Zobrist hash of the board state.
+    /// Two equal boards always have the same hash, and different boards very likely have
+    /// different hashes.
+    pub fn hash(&self) -> u64 {
+        let mut hash = KEYS[WIDTH_OFFSET + self.width as usize] ^ KEYS[HEIGHT_OFFSET + self.height as usize];
+        let mut add = |offset: usize, (x, y): (u8, u8)| hash ^= KEYS[offset + x as usize + y as usize * MAX_SIZE];
+        match &self.discs {
+            Discs::Words { black, white, .. } => {
+                fast::squares(*black).for_each(|square| add(BLACK_OFFSET, square));
+                fast::squares(*white).for_each(|square| add(WHITE_OFFSET, square));
+            },
+            Discs::Bitmaps { black, white, .. } => {
+                black.clone().for_each(|square| add(BLACK_OFFSET, square));
+                white.clone().for_each(|square| add(WHITE_OFFSET, square));
+            },
+        }
+        match self.player {
+            Some(Player::Black) => { },
+            Some(Player::White) => hash ^= KEYS[WHITE_TO_MOVE],
+            None => hash ^= KEYS[GAME_OVER],
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Square;
+
+    #[test]
+    fn distinct_keys() {
+        let mut keys = KEYS.to_vec();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), KEY_COUNT);
+    }
+
+    #[test]
+    fn hashing() {
+        for s in (2..=14).filter(|&s| s % 2 == 0) {
+            assert_eq!(Board::new(s).hash(), Board::new(s).hash());
+            assert_ne!(Board::new(s).hash(), Board::new(s).set(0, 0, Square::Disc(Player::Black)).hash());
+        }
+        assert_ne!(Board::new(4).hash(), Board::new(6).hash());
+        assert_ne!(Board::rectangular(4, 6).hash(), Board::rectangular(6, 4).hash());
+
+        let mut board = Board::new(8);
+        let hash = board.hash();
+        board.player = Some(Player::White);
+        assert_ne!(board.hash(), hash);
+        board.player = None;
+        assert_ne!(board.hash(), hash);
+    }
+
+    #[test]
+    fn transpositions() {
+        // Enumerate the positions after 4 plies, some of which are reached by several move orders.
+        let mut boards = vec!(Board::new(8));
+        for _ in 0..4 {
+            boards = boards.iter()
+                .flat_map(|b| b.moves().into_iter().map(|m| b.play(&m).unwrap()))
+                .collect();
+        }
+
+        let mut transpositions = 0;
+        for (i, a) in boards.iter().enumerate() {
+            for b in &boards[i + 1..] {
+                assert_eq!(a == b, a.hash() == b.hash());
+                if a == b {
+                    transpositions += 1;
+                }
+            }
+        }
+        assert!(transpositions > 0);
+    }
+}
@@ -0,0 +1,433 @@
+use crate::{
+    board::{Board, Move, ParsingError, Player, Square, Transform},
+    computer::{self, Score, SearchOptions},
+    game::Game,
+};
+
+use std::{collections::BTreeMap, fs, path::Path};
+use rand::{Rng, seq::IndexedRandom};
+
+// A book file starts with this tag, the size of the board and the number of positions. Each
+// position follows as the player to move (0 for black, 1 for white), the discs of black and white
+// (16 bytes each) and its number of moves, each move being its square `x + y * size`, its score
+// (2 bytes, `i16::MIN` if unknown), its number of games and its points (4 bytes each).
+// Numbers are little-endian.
+const MAGIC: &[u8; 4] = b"RVBK";
+const HEADER_SIZE: usize = 9;
+const POSITION_SIZE: usize = 34;
+const MOVE_SIZE: usize = 11;
+const UNKNOWN: i16 = i16::MIN;
+// Positions are keyed on 128 bits, so books are limited to square boards of size at most 10.
+const MAX_SIZE: u8 = 10;
+
+/// A book of opening moves for a board size, keyed by positions reduced by the symmetries of the
+/// board, so that symmetric positions share their moves.
+/// Moves are scored by searches, and counted in the games the book learned from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Book {
+    size: u8,
+    positions: BTreeMap<Key, Vec<BookMove>>,
+}
+
+/// A move of the book, with what is known about it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookMove {
+    pub m: Move,
+    /// The final disc difference expected after the move for the player who plays it, if the move
+    /// was searched.
+    pub score: Option<i16>,
+    /// The number of finished games where the move was played.
+    pub games: u32,
+    /// The points the player of the move got in those games: 2 per win and 1 per draw.
+    pub points: u32,
+}
+
+// The discs of a position in its canonical orientation, the square at column `x` and row `y`
+// being the bit `x + y * size`, and whether white is to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Key {
+    black: u128,
+    white: u128,
+    white_to_move: bool,
+}
+
+impl Book {
+    /// Create an empty book for the given board size.
+    /// Return `BadSize` for the sizes books do not support.
+    pub fn new(size: u8) -> Result<Self, ParsingError> {
+        if !is_valid_size(size) {
+            return Err(ParsingError::BadSize);
+        }
+        Ok(Self { size, positions: BTreeMap::new() })
+    }
+
+    /// Return the size of the boards of the book.
+    pub fn size(&self) -> u8 {
+        self.size
+    }
+
+    /// Return the number of positions of the book.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Load a book written by `save`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ParsingError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Write the book to a file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ParsingError> {
+        Ok(fs::write(path, self.to_bytes())?)
+    }
+
+    /// Read a book from its binary representation.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParsingError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(ParsingError::EmptyFile);
+        }
+        if &bytes[..4] != MAGIC {
+            return Err(ParsingError::Generic);
+        }
+        let size = bytes[4];
+        let count = u32::from_le_bytes(bytes[5..9].try_into().expect("slice of 4 bytes"));
+
+        let mut book = Self::new(size)?;
+        let mut rest = &bytes[HEADER_SIZE..];
+        for _ in 0..count {
+            let (position, tail) = rest.split_at_checked(POSITION_SIZE).ok_or(ParsingError::UnterminatedRecord)?;
+            let key = Key {
+                white_to_move: position[0] != 0,
+                black: u128::from_le_bytes(position[1..17].try_into().expect("slice of 16 bytes")),
+                white: u128::from_le_bytes(position[17..33].try_into().expect("slice of 16 bytes")),
+            };
+            let (moves, tail) = tail.split_at_checked(MOVE_SIZE * position[33] as usize).ok_or(ParsingError::UnterminatedRecord)?;
+            let moves = moves.chunks(MOVE_SIZE).map(|bytes| {
+                if bytes[0] >= size * size {
+                    return Err(ParsingError::InvalidMove(bytes[0].to_string()));
+                }
+                let score = i16::from_le_bytes([bytes[1], bytes[2]]);
+                Ok(BookMove {
+                    m: Move::new(bytes[0] % size, bytes[0] / size),
+                    score: (score != UNKNOWN).then_some(score),
+                    games: u32::from_le_bytes(bytes[3..7].try_into().expect("slice of 4 bytes")),
+                    points: u32::from_le_bytes(bytes[7..11].try_into().expect("slice of 4 bytes")),
+                })
+            }).collect::<Result<_, _>>()?;
+            book.positions.insert(key, moves);
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            return Err(ParsingError::InconsistentSize);
+        }
+        Ok(book)
+    }
+
+    /// Return the binary representation of the book.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(self.size);
+        bytes.extend((self.positions.len() as u32).to_le_bytes());
+        for (key, moves) in &self.positions {
+            bytes.push(key.white_to_move as u8);
+            bytes.extend(key.black.to_le_bytes());
+            bytes.extend(key.white.to_le_bytes());
+            bytes.push(moves.len() as u8);
+            for m in moves {
+                bytes.push(m.m.x() + m.m.y() * self.size);
+                bytes.extend(m.score.unwrap_or(UNKNOWN).to_le_bytes());
+                bytes.extend(m.games.to_le_bytes());
+                bytes.extend(m.points.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Learn the moves of the first `plies` plies of a game, counting the points of finished
+    /// games for the players of the moves.
+    /// Return `InconsistentSize` for a game on a board of another shape than the book's.
+    pub fn add_game(&mut self, game: &Game, plies: usize) -> Result<(), ParsingError> {
+        // The size of the book is valid, so the boards of the game can be keyed.
+        if (game.start().width(), game.start().height()) != (self.size, self.size) {
+            return Err(ParsingError::InconsistentSize);
+        }
+        let mut game = game.clone();
+        game.jump(game.plies().len());
+        let last = game.board().clone();
+        game.jump(0);
+
+        for _ in 0..plies.min(game.plies().len()) {
+            let board = game.board().clone();
+            let ply = game.ply();
+            game.redo();
+            let (Some(m), Some(player)) = (game.plies()[ply], board.player) else { continue; };
+
+            let (key, transforms) = canonical(&board);
+            let m = reduce(self.size, &transforms, m);
+            let moves = self.positions.entry(key).or_default();
+            let i = match moves.iter().position(|b| b.m == m) {
+                Some(i) => i,
+                None => {
+                    moves.push(BookMove { m, score: None, games: 0, points: 0 });
+                    moves.len() - 1
+                },
+            };
+            if last.player.is_none() {
+                let (black, white) = last.score();
+                let difference = match player {
+                    Player::Black => black as i32 - white as i32,
+                    Player::White => white as i32 - black as i32,
+                };
+                moves[i].games += 1;
+                moves[i].points += (difference.signum() + 1) as u32;
+            }
+        }
+        Ok(())
+    }
+
+    /// Score every move of every position of the book that is not scored yet, by searching the
+    /// position it leads to with the given options.
+    /// Return the number of moves searched.
+    pub fn evaluate(&mut self, options: &SearchOptions) -> usize {
+        let size = self.size;
+        let mut searched = 0;
+        for (key, moves) in self.positions.iter_mut() {
+            let board = key.board(size);
+            let (_, transforms) = canonical(&board);
+            // All the moves of the position are searched, so that the best ones can be told apart.
+            for m in board.moves() {
+                let m = reduce(size, &transforms, m);
+                if !moves.iter().any(|b| b.m == m) {
+                    moves.push(BookMove { m, score: None, games: 0, points: 0 });
+                }
+            }
+            for book_move in moves.iter_mut().filter(|b| b.score.is_none()) {
+                let child = board.play(&book_move.m).expect("book moves are valid");
+                book_move.score = Some(score(&board, &child, options));
+                searched += 1;
+            }
+        }
+        searched
+    }
+
+    /// Return the moves of the book for the position, if any, in the orientation of the board.
+    pub fn moves(&self, board: &Board) -> Vec<BookMove> {
+        if (board.width(), board.height()) != (self.size, self.size) || board.player.is_none() {
+            return vec!();
+        }
+        let (key, transforms) = canonical(board);
+        self.positions.get(&key).map_or(vec!(), |moves| {
+            moves.iter().map(|b| BookMove { m: b.m.transform(self.size, self.size, transforms[0].inverse()), ..*b }).collect()
+        })
+    }
+
+    /// Choose a move for the position at random among the scored book moves at most `margin` discs
+    /// below the best one, if there is any.
+    pub fn probe(&self, board: &Board, margin: i16, rng: &mut impl Rng) -> Option<Move> {
+        let moves = self.moves(board);
+        let best = moves.iter().filter_map(|b| b.score).max()?;
+        let candidates: Vec<Move> = moves.iter()
+            .filter(|b| b.score.is_some_and(|s| s >= best.saturating_sub(margin)))
+            .map(|b| b.m)
+            .collect();
+        candidates.choose(rng).copied()
+    }
+}
+
+impl Key {
+    // Return the position the key stands for.
+    fn board(&self, size: u8) -> Board {
+        let grid: Vec<Vec<Square>> = (0..size).map(|y| (0..size).map(|x| {
+            let bit = 1 << (x as u32 + y as u32 * size as u32);
+            if self.black & bit != 0 {
+                Square::Disc(Player::Black)
+            } else if self.white & bit != 0 {
+                Square::Disc(Player::White)
+            } else {
+                Square::Empty
+            }
+        }).collect()).collect();
+        Board::from_grid(&grid, if self.white_to_move { Player::White } else { Player::Black })
+    }
+}
+
+// Return the score of a move, from the point of view of the player who played it.
+fn score(board: &Board, child: &Board, options: &SearchOptions) -> i16 {
+    let squares = board.width() as i32 * board.height() as i32;
+    let player = board.player.expect("book positions have a player to move");
+    let score = match child.player {
+        None => {
+            let (black, white) = child.score();
+            return match player {
+                Player::Black => black as i16 - white as i16,
+                Player::White => white as i16 - black as i16,
+            };
+        },
+        Some(next) => {
+            let report = computer::search(child, &SearchOptions { timeout: None, ..options.clone() });
+            let score = match report.score {
+                Some(Score::Heuristic(s) | Score::Exact(s)) => s,
+                None => 0,
+            };
+            if next == player { score } else { score.saturating_neg() }
+        },
+    };
+    // Won and lost positions are evaluated to the extremes.
+    score.clamp(-squares, squares) as i16
+}
+
+fn is_valid_size(size: u8) -> bool {
+    (2..=MAX_SIZE).contains(&size)
+}
+
+// Return the key of the canonical board of the position, and the transforms to it.
+// The board must be square, of a size books support.
+fn canonical(board: &Board) -> (Key, Vec<Transform>) {
+    let transforms = board.canonical_transforms();
+    let canonical = board.transform(transforms[0]);
+    let size = canonical.width();
+    let (mut black, mut white) = (0, 0);
+    for y in 0..size {
+        for x in 0..size {
+            let bit = 1 << (x as u32 + y as u32 * size as u32);
+            match canonical.get(x, y) {
+                Square::Disc(Player::Black) => black |= bit,
+                Square::Disc(Player::White) => white |= bit,
+                Square::Empty => { },
+            }
+        }
+    }
+    (Key { black, white, white_to_move: board.player == Some(Player::White) }, transforms)
+}
+
+// Return the move on the canonical board given by the transforms of `canonical`, the same for all
+// the moves that are symmetric in a symmetric position.
+fn reduce(size: u8, transforms: &[Transform], m: Move) -> Move {
+    transforms.iter()
+        .map(|&t| m.transform(size, size, t))
+        .min_by_key(|m| (m.y(), m.x()))
+        .expect("a position has at least one canonical transform")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    fn game(size: u8, sequence: &str) -> Game {
+        let mut game = Game::new(Board::new(size));
+        game.play_sequence(sequence).unwrap();
+        game
+    }
+
+    #[test]
+    fn symmetries() {
+        // The 4 first moves of the game lead to the same canonical position.
+        let board = Board::new(8);
+        let keys: Vec<Key> = board.moves().iter().map(|m| canonical(&board.play(m).unwrap()).0).collect();
+        assert!(keys.iter().all(|k| *k == keys[0]));
+        let (key, transforms) = canonical(&board);
+        assert_ne!(key, keys[0]);
+        // The starting position is symmetric along both diagonals, so its moves are the same.
+        assert_eq!(transforms.len(), 4);
+        let moves: Vec<Move> = board.moves().iter().map(|&m| reduce(8, &transforms, m)).collect();
+        assert!(moves.iter().all(|&m| m == moves[0]));
+    }
+
+    #[test]
+    fn learning() {
+        let mut book = Book::new(8).unwrap();
+        book.add_game(&game(8, "f5d6c3"), 2).unwrap();
+        // The same opening, reflected.
+        book.add_game(&game(8, "e6f4"), 2).unwrap();
+        assert_eq!(book.len(), 2);
+
+        let board = Board::new(8);
+        let moves = book.moves(&board);
+        assert_eq!(moves.len(), 1);
+        assert!(board.is_valid_move(&moves[0].m));
+        // Unfinished games are not counted.
+        assert_eq!(moves[0].games, 0);
+        assert_eq!(book.moves(&board.play(&"F5".parse().unwrap()).unwrap())[0].m, "D6".parse().unwrap());
+        assert_eq!(book.moves(&board.play(&"E6".parse().unwrap()).unwrap())[0].m, "F4".parse().unwrap());
+
+        // A finished 4x4 game counts for both players.
+        let mut small = Book::new(4).unwrap();
+        let mut finished = Game::new(Board::new(4));
+        while let Some(&m) = finished.board().moves().first() {
+            finished.play(&m);
+        }
+        small.add_game(&finished, 60).unwrap();
+        let (black, white) = finished.board().score();
+        let points = small.moves(&Board::new(4))[0].points;
+        assert_eq!(points as i32, (black as i32 - white as i32).signum() + 1);
+        assert_eq!(small.add_game(&game(8, "f5"), 1), Err(ParsingError::InconsistentSize));
+        assert_eq!(small.add_game(&Game::new(Board::rectangular(4, 6)), 1), Err(ParsingError::InconsistentSize));
+    }
+
+    #[test]
+    fn probing() {
+        let mut book = Book::new(6).unwrap();
+        let board = Board::new(6);
+        book.add_game(&game(6, &board.moves()[0].to_string()), 1).unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        // Moves that are not scored are not played.
+        assert_eq!(book.probe(&board, 0, &mut rng), None);
+
+        let options = SearchOptions { depth: Some(2), ..Default::default() };
+        // The 4 first moves are symmetric, so there is only one to search.
+        assert_eq!(book.evaluate(&options), 1);
+        assert_eq!(book.evaluate(&options), 0);
+        let m = book.probe(&board, 0, &mut rng).unwrap();
+        assert!(board.is_valid_move(&m));
+        assert_eq!(book.probe(&Board::new(8), 0, &mut rng), None);
+        assert_eq!(book.probe(&board.play(&m).unwrap(), 0, &mut rng), None);
+    }
+
+    #[test]
+    fn near_best() {
+        let mut book = Book::new(8).unwrap();
+        let board = Board::new(8).play(&"F5".parse().unwrap()).unwrap();
+        let (key, transforms) = canonical(&board);
+        let scored = |m: &str, score| BookMove { m: reduce(8, &transforms, m.parse().unwrap()), score: Some(score), games: 0, points: 0 };
+        book.positions.insert(key, vec!(scored("D6", 4), scored("F4", 3), scored("F6", -2)));
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let chosen: Vec<String> = (0..50).map(|_| book.probe(&board, 0, &mut rng).unwrap().to_string()).collect();
+        assert!(chosen.iter().all(|m| m == "D6"));
+        let chosen: Vec<String> = (0..50).map(|_| book.probe(&board, 1, &mut rng).unwrap().to_string()).collect();
+        assert!(chosen.contains(&"D6".into()) && chosen.contains(&"F4".into()) && !chosen.contains(&"F6".into()));
+    }
+
+    #[test]
+    fn files() {
+        for size in [6, 7, 8, 10] {
+            let mut book = Book::new(size).unwrap();
+            let mut game = Game::new(Board::new(size));
+            for _ in 0..6 {
+                let m = game.board().moves()[0];
+                game.play(&m);
+            }
+            book.add_game(&game, 6).unwrap();
+            book.evaluate(&SearchOptions { depth: Some(1), ..Default::default() });
+            assert_eq!(Book::from_bytes(&book.to_bytes()), Ok(book.clone()));
+
+            let directory = tempfile::tempdir().unwrap();
+            let path = directory.path().join("book.bin");
+            book.save(&path).unwrap();
+            assert_eq!(Book::load(&path), Ok(book));
+        }
+
+        let bytes = Book::new(8).unwrap().to_bytes();
+        assert_eq!(Book::from_bytes(&bytes[..4]), Err(ParsingError::EmptyFile));
+        assert_eq!(Book::from_bytes(b"RVBK\x0b\x00\x00\x00\x00"), Err(ParsingError::BadSize));
+        assert_eq!(Book::new(12), Err(ParsingError::BadSize));
+        let mut truncated = bytes.clone();
+        truncated[5] = 1;
+        assert_eq!(Book::from_bytes(&truncated), Err(ParsingError::UnterminatedRecord));
+    }
+}
@@ -1,14 +1,35 @@
+mod endgame;
+mod eval;
+mod mcts;
+mod pattern;
+mod tt;
+
 use crate::board::{Board, Move, Player};
+pub use endgame::{EndgameMode, Solution, solve};
+pub use eval::{Corners, DiscCount, Evaluator, Frontier, Mobility, Stability, WeightedSum};
+pub use mcts::{Mcts, MctsOptions, Playout};
+pub use pattern::{Example, Patterns, Training};
+pub use tt::Pack;
+use endgame::Solver;
+use tt::{Bound, Entry, TranspositionTable};
 
 use std::{
-    cmp::{max, min, Ord, Ordering},
+    cmp::{max, min, Ord, Reverse},
+    fmt,
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+    thread,
     time::{Duration, Instant}
 };
-use rand::{rng, seq::SliceRandom};
-
-const DEPTH: u8 = 10;
+use rand::{SeedableRng, rng, rngs::StdRng, seq::SliceRandom};
 
-type Heuristic<T> = fn(&Board, &Player) -> T;
+const TT_SIZE: usize = 1 << 18;
+// Solving 8x8 positions of 14, 16, 18 and 20 empty squares takes about 0.09, 0.7, 3 and 25 seconds
+// on average, so exact solves are left to the positions that fit the default 5 seconds per move.
+// Longer analyses can raise `endgame_threshold` up to 21 to solve 20 empty squares.
+const ENDGAME_THRESHOLD: u8 = 14;
+// The depth of the transposition table entries of positions searched to the end of the game, with
+// no leaf cut off by the depth limit.
+const EXHAUSTED: u8 = u8::MAX;
 
 pub trait BoundedOrd: Ord {
     const MIN: Self;
@@ -20,132 +41,730 @@ impl BoundedOrd for i16 {
     const MAX: i16 = i16::MAX;
 }
 
-#[allow(dead_code)]
-pub fn minmax(board: &Board, timeout: Duration) -> Option<Move> {
-    generic_minmax(board, timeout, heuristic)
+/// The limits and settings of a search.
+#[derive(Debug, Clone)]
+pub struct SearchOptions {
+    /// The time after which the search is stopped, if any.
+    pub timeout: Option<Duration>,
+    /// The maximum depth of the search, if any.
+    pub depth: Option<u8>,
+    /// Whether to sort moves so that the most promising ones are searched first.
+    pub move_ordering: bool,
+    /// The number of entries of the transposition table, 0 to disable it.
+    pub tt_size: usize,
+    /// The seed used to shuffle the moves at the root, for reproducible searches.
+    pub seed: Option<u64>,
+    /// The number of empty squares below which the game is solved exactly instead of searched,
+    /// unless `depth` is too small to reach the end of the game.
+    pub endgame_threshold: u8,
+    /// What the endgame solver computes.
+    pub endgame_mode: EndgameMode,
+    /// The number of threads searching together and sharing the transposition table. With a
+    /// single thread, the search runs on the calling thread and is reproducible given a seed.
+    /// The endgame solver always runs on the calling thread only.
+    pub threads: usize,
 }
 
-#[allow(dead_code)]
-fn generic_minmax<T: Ord>(board: &Board, timeout: Duration, heuristic: Heuristic<T>) -> Option<Move> {
-    let player = board.player?;
+/// The evaluation of a position found by a search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Score<S> {
+    /// The evaluation of the `Evaluator` at the depth searched.
+    Heuristic(S),
+    /// The final disc difference with perfect play, found by the endgame solver.
+    /// In `WinLossDraw` mode, only its sign is computed.
+    Exact(i32),
+}
 
-    let start = Instant::now();
-    let end = start + timeout;
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Some(Duration::from_secs(5)),
+            depth: None,
+            move_ordering: true,
+            tt_size: TT_SIZE,
+            seed: None,
+            endgame_threshold: ENDGAME_THRESHOLD,
+            endgame_mode: EndgameMode::Exact,
+            threads: 1,
+        }
+    }
+}
 
-    let depth = DEPTH;
-    let mut moves = board.moves();
-    moves.shuffle(&mut rng());
-    moves.into_iter().max_by_key(|m| {
-        helper(&board.play(m).unwrap(), &player, depth - 1, end, heuristic)
-    })
+/// The outcome of a search.
+#[derive(Debug, Clone)]
+pub struct SearchReport<S = i32> {
+    /// The best move found, if the player to move has any.
+    pub best_move: Option<Move>,
+    /// The evaluation of the position, from the point of view of the player to move, if a search
+    /// was completed.
+    pub score: Option<Score<S>>,
+    /// The moves expected to be played from the position, starting with the best move.
+    pub pv: Vec<Move>,
+    /// The depth of the last completed iteration.
+    pub depth: u8,
+    /// The number of nodes visited.
+    pub nodes: u64,
+    /// The time the search took.
+    pub elapsed: Duration,
 }
 
-#[allow(dead_code)]
-fn helper<T: Ord>(board: &Board, player: &Player, depth: u8, end: Instant, heuristic: Heuristic<T>) -> T {
-    if depth == 0 || board.player.is_none() || Instant::now() >= end {
-        return heuristic(board, player);
+impl<S> SearchReport<S> {
+    // The report of a search of a finished game.
+    fn empty() -> Self {
+        Self { best_move: None, score: None, pv: vec!(), depth: 0, nodes: 0, elapsed: Duration::ZERO }
     }
 
-    let current_player = board.player.unwrap();
-    let maximize = current_player == *player;
+    /// Return the number of nodes visited per second.
+    pub fn nps(&self) -> f64 {
+        self.nodes as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
 
-    let branches = board.moves().into_iter().map(|m| {
-        helper(&board.play(&m).unwrap(), player, depth - 1, end, heuristic)
-    });
+impl<S: fmt::Display> SearchReport<S> {
+    /// Return the report as a JSON object, with the fields `move`, `score`, `exact`, `pv`,
+    /// `depth`, `nodes`, `nps` and `elapsed` (in seconds).
+    pub fn to_json(&self) -> String {
+        let best_move = self.best_move.map_or("null".into(), |m| format!("\"{}\"", m));
+        let (score, exact) = match &self.score {
+            Some(Score::Heuristic(s)) => (s.to_string(), false),
+            Some(Score::Exact(s)) => (s.to_string(), true),
+            None => ("null".into(), false),
+        };
+        let pv: Vec<String> = self.pv.iter().map(|m| format!("\"{}\"", m)).collect();
+        format!("{{\"move\":{},\"score\":{},\"exact\":{},\"pv\":[{}],\"depth\":{},\"nodes\":{},\"nps\":{:.0},\"elapsed\":{:.3}}}",
+            best_move, score, exact, pv.join(","), self.depth, self.nodes, self.nps(), self.elapsed.as_secs_f64())
+    }
+}
 
-    if maximize {
-        branches.max()
-    } else {
-        branches.min()
-    }.unwrap()
+impl<S: fmt::Display> fmt::Display for Score<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Score::Heuristic(s) => write!(f, "{}", s),
+            Score::Exact(s) => write!(f, "{} (exact)", s),
+        }
+    }
+}
+
+impl<S: fmt::Display> fmt::Display for SearchReport<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(best_move) = self.best_move else {
+            return write!(f, "No move to search.");
+        };
+        let score = self.score.as_ref().map_or("unknown".into(), |s| s.to_string());
+        let pv: Vec<String> = self.pv.iter().map(|m| m.to_string()).collect();
+        write!(f, "Best move {}, score {}, depth {}, PV {}, {} nodes in {:.3} s ({:.0} nodes/s).",
+            best_move, score, self.depth, pv.join(" "), self.nodes, self.elapsed.as_secs_f64(), self.nps())
+    }
 }
 
 pub fn ab_minmax(board: &Board, timeout: Duration) -> Option<Move> {
-    generic_ab_minmax(board, timeout, heuristic)
+    search(board, &SearchOptions { timeout: Some(timeout), ..Default::default() }).best_move
+}
+
+/// Search for the best move with alpha-beta pruning within the given limits, evaluating positions
+/// by their number of discs.
+pub fn search(board: &Board, options: &SearchOptions) -> SearchReport {
+    generic_ab_minmax(board, options, &DiscCount)
 }
 
-fn generic_ab_minmax<T: BoundedOrd + Copy>(board: &Board, timeout: Duration, heuristic: Heuristic<T>) -> Option<Move> {
-    let player = &board.player?;
+/// Search for the best move with alpha-beta pruning within the given limits, evaluating positions
+/// with the given `Evaluator`.
+pub fn search_with<E: Evaluator + Sync>(board: &Board, options: &SearchOptions, evaluator: &E) -> SearchReport<E::Score> {
+    generic_ab_minmax(board, options, evaluator)
+}
+
+// Iterative deepening: search at depth 1, 2, 3, ... until the deadline is reached or an iteration
+// reaches the end of the game on every line, and return the best move of the last completed
+// iteration.
+// With several threads, helpers follow the iterations of the main thread, every other one a ply
+// deeper, and in their own order of the moves. They share the transposition table with the main thread,
+// which they fill with results it can use, and stop once it is done (Lazy SMP).
+fn generic_ab_minmax<E: Evaluator + Sync>(board: &Board, options: &SearchOptions, evaluator: &E) -> SearchReport<E::Score> {
+    let Some(player) = board.player else {
+        return SearchReport::empty();
+    };
 
     let start = Instant::now();
-    let end = start + timeout;
+    let end = options.timeout.map(|t| start + t);
+
+    if board.empty_count() < options.endgame_threshold && options.depth.is_none_or(|d| d >= board.empty_count()) {
+        return endgame(board, options.endgame_mode, start, end);
+    }
+
+    let tt = TranspositionTable::new(options.tt_size);
+    let stop = AtomicBool::new(false);
+    let iteration = AtomicU8::new(1);
+    let max_depth = options.depth.unwrap_or(EXHAUSTED - 1);
+
+    thread::scope(|scope| {
+        let helpers: Vec<_> = (1..options.threads.max(1)).map(|t| {
+            let (tt, stop, iteration) = (&tt, &stop, &iteration);
+            let seed = options.seed.map(|seed| seed.wrapping_add(t as u64));
+            scope.spawn(move || {
+                let mut search = Search::new(board, player, end, evaluator, options, tt, stop);
+                let moves = search.root_moves(board, seed);
+                search.deepen(board, moves, iteration, Some(t as u8 % 2), max_depth);
+                search.nodes
+            })
+        }).collect();
+
+        let mut search = Search::new(board, player, end, evaluator, options, &tt, &stop);
+        let moves = search.root_moves(board, options.seed);
+        let (optimal_move, score, completed_depth) = search.deepen(board, moves, &iteration, None, max_depth);
+        stop.store(true, Ordering::Relaxed);
+        let helper_nodes: u64 = helpers.into_iter().map(|h| h.join().expect("helpers do not panic")).sum();
+
+        SearchReport {
+            best_move: Some(optimal_move),
+            score: score.map(Score::Heuristic),
+            pv: principal_variation(board, optimal_move, &tt, completed_depth.max(1) as usize),
+            depth: completed_depth,
+            nodes: search.nodes + helper_nodes,
+            elapsed: start.elapsed(),
+        }
+    })
+}
 
-    let mut moves = board.moves();
-    moves.shuffle(&mut rng());
+// Solve the game exactly. If the deadline is reached first, fall back on the best move among
+// those already solved.
+fn endgame<S>(board: &Board, mode: EndgameMode, start: Instant, end: Option<Instant>) -> SearchReport<S> {
+    let mut solver = Solver::new(board, end);
+    let mut best_move = None;
+    let solution = solver.root(board, mode, |m, _| best_move = Some(m));
+
+    let (score, depth) = match solution {
+        Some((m, score)) => {
+            best_move = m;
+            (Some(Score::Exact(score)), board.empty_count())
+        },
+        None => (None, 0),
+    };
 
-    let depth = DEPTH;
-    let mut alpha = T::MIN;
-    let beta = T::MAX;
-    let mut optimal_move = moves[0];
-    let mut optimal_eval = T::MIN;
-    for m in moves {
-        let eval = ab_helper(&board.play(&m).unwrap(), player, depth - 1, alpha, beta, end, heuristic);
+    let best_move = best_move.or_else(|| board.moves().first().copied());
+    SearchReport {
+        best_move,
+        score,
+        pv: best_move.map_or(vec!(), |m| principal_variation(board, m, &solver.tt, board.empty_count() as usize)),
+        depth,
+        nodes: solver.nodes,
+        elapsed: start.elapsed(),
+    }
+}
 
-        if eval == optimal_eval {
-            continue;
+// Follow the best moves stored in the transposition table from the best move of the root, for at
+// most `length` moves. The variation is cut short where entries were overwritten.
+fn principal_variation<T: Pack>(board: &Board, best_move: Move, tt: &TranspositionTable<T>, length: usize) -> Vec<Move> {
+    let mut pv = vec!(best_move);
+    let mut board = board.play(&best_move).expect("the best move is valid");
+    while pv.len() < length {
+        let Some(m) = tt.probe(board.hash()).and_then(|e| e.best_move) else { break; };
+        let Some(next) = board.play(&m) else { break; };
+        pv.push(m);
+        board = next;
+    }
+    pv
+}
+
+// The state shared by all the nodes of an alpha-beta search on a thread.
+// Evaluations are always from the point of view of `player`, the player to move at the root.
+struct Search<'a, E: Evaluator> {
+    player: Player,
+    end: Option<Instant>,
+    // Set when the helpers of a parallel search should stop.
+    stop: &'a AtomicBool,
+    evaluator: &'a E,
+    tt: &'a TranspositionTable<E::Score>,
+    move_ordering: bool,
+    width: u8,
+    height: u8,
+    // Moves that caused a cutoff, indexed by the square they are played on.
+    history: Vec<u64>,
+    // The two last moves that caused a cutoff, indexed by the remaining depth.
+    killers: Vec<[Option<Move>; 2]>,
+    // Whether a leaf was cut off by the depth limit in the node being searched, rather than being
+    // at the end of the game.
+    cut_off: bool,
+    nodes: u64,
+}
+
+impl<'a, E: Evaluator> Search<'a, E> {
+    fn new(board: &Board, player: Player, end: Option<Instant>, evaluator: &'a E, options: &SearchOptions,
+        tt: &'a TranspositionTable<E::Score>, stop: &'a AtomicBool) -> Self {
+        let (width, height) = (board.width(), board.height());
+        Self {
+            player,
+            end,
+            stop,
+            evaluator,
+            tt,
+            move_ordering: options.move_ordering,
+            width,
+            height,
+            history: vec![0; width as usize * height as usize],
+            killers: vec![[None; 2]; EXHAUSTED as usize],
+            cut_off: false,
+            nodes: 0,
         }
+    }
 
-        optimal_eval = max(eval, optimal_eval);
-        alpha = max(optimal_eval, alpha);
+    // Return the moves of the root, shuffled and then sorted by the move ordering.
+    fn root_moves(&self, board: &Board, seed: Option<u64>) -> Vec<Move> {
+        let mut moves = board.moves();
+        match seed {
+            Some(seed) => moves.shuffle(&mut StdRng::seed_from_u64(seed)),
+            None => moves.shuffle(&mut rng()),
+        }
+        if self.move_ordering {
+            let children = moves.iter().map(|m| (*m, board.play(m).unwrap())).collect();
+            moves = self.order(board, children, None, 0).into_iter().map(|(m, _)| m).collect();
+        }
+        moves
+    }
 
-        if eval == optimal_eval {
+    // Search the root at increasing depths up to `last`, until the deadline or until an iteration
+    // cuts off no leaf, which means the whole game tree was explored.
+    // The main thread, without `offset`, publishes the depth of each of its iterations in
+    // `iteration`. Helpers search `offset` plies deeper than the iteration of the main thread, and
+    // always deeper than their own previous iteration.
+    // Return the best move and evaluation of the last completed iteration, and its depth.
+    fn deepen(&mut self, board: &Board, mut moves: Vec<Move>, iteration: &AtomicU8, offset: Option<u8>, last: u8)
+        -> (Move, Option<E::Score>, u8) {
+        let mut optimal_move = moves[0];
+        let mut score = None;
+        let mut completed_depth = 0;
+        let mut depth = iteration.load(Ordering::Relaxed) + offset.unwrap_or(0);
+        while depth <= last {
+            if offset.is_none() {
+                iteration.store(depth, Ordering::Relaxed);
+            }
+            let Some((m, eval)) = self.root(board, &moves, depth) else {
+                break;
+            };
             optimal_move = m;
+            score = Some(eval);
+            completed_depth = depth;
+            if !self.cut_off {
+                break;
+            }
+
+            // Search the best move of this iteration first in the next one.
+            let i = moves.iter().position(|n| *n == m).expect("the best move is one of the moves");
+            moves[..=i].rotate_right(1);
+            depth = match offset {
+                None => depth + 1,
+                Some(offset) => max(depth + 1, iteration.load(Ordering::Relaxed) + offset),
+            };
         }
+        (optimal_move, score, completed_depth)
+    }
+
+    // Search every move of the root to a fixed depth.
+    // Return `None` if the deadline was reached before the search completed.
+    fn root(&mut self, board: &Board, moves: &[Move], depth: u8) -> Option<(Move, E::Score)> {
+        self.nodes += 1;
+        self.cut_off = false;
+
+        let mut alpha = E::Score::MIN;
+        let beta = E::Score::MAX;
+        let mut optimal_move = moves[0];
+        let mut optimal_eval = E::Score::MIN;
+        for &m in moves {
+            let eval = self.ab_helper(&board.play(&m).unwrap(), depth - 1, alpha, beta)?;
+
+            if eval == optimal_eval {
+                continue;
+            }
 
-        if alpha >= beta {
-            break;
+            optimal_eval = max(eval, optimal_eval);
+            alpha = max(optimal_eval, alpha);
+
+            if eval == optimal_eval {
+                optimal_move = m;
+            }
+
+            if alpha >= beta {
+                break;
+            }
         }
+
+        Some((optimal_move, optimal_eval))
     }
 
-    Some(optimal_move)
+    // Return `None` if the deadline was reached or the search stopped, in which case the partial
+    // result is worthless.
+    fn ab_helper(&mut self, board: &Board, depth: u8, mut alpha: E::Score, mut beta: E::Score) -> Option<E::Score> {
+        if self.end.is_some_and(|end| Instant::now() >= end) || self.stop.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.nodes += 1;
+        if depth == 0 || board.player.is_none() {
+            self.cut_off |= board.player.is_some();
+            return Some(self.evaluator.evaluate(board, &self.player));
+        }
+
+        let outer_cut_off = std::mem::replace(&mut self.cut_off, false);
+        let hash = board.hash();
+        let mut tt_move = None;
+        if let Some(entry) = self.tt.probe(hash) {
+            if entry.depth >= depth {
+                // Entries may have been searched to a depth limit, unless they reached the end of
+                // the game.
+                self.cut_off = entry.depth != EXHAUSTED;
+                match entry.bound {
+                    Bound::Exact => {
+                        self.cut_off |= outer_cut_off;
+                        return Some(entry.eval);
+                    },
+                    Bound::Lower => alpha = max(entry.eval, alpha),
+                    Bound::Upper => beta = min(entry.eval, beta),
+                }
+                if alpha >= beta {
+                    self.cut_off |= outer_cut_off;
+                    return Some(entry.eval);
+                }
+            }
+            tt_move = entry.best_move;
+        }
+
+        let current_player = board.player.unwrap();
+        let maximize = current_player == self.player;
+        let (alpha_0, beta_0) = (alpha, beta);
+
+        let children = board.moves().into_iter().map(|m| (m, board.play(&m).unwrap())).collect();
+        let children = self.order(board, children, tt_move, depth);
+
+        let mut optimal_eval = if maximize { E::Score::MIN } else { E::Score::MAX };
+        let mut optimal_move = children[0].0;
+        for (m, child) in children {
+            let eval = self.ab_helper(&child, depth - 1, alpha, beta)?;
+
+            if eval == optimal_eval {
+                continue;
+            }
+
+            if maximize {
+                optimal_eval = max(eval, optimal_eval);
+                alpha = max(optimal_eval, alpha);
+            } else {
+                optimal_eval = min(eval, optimal_eval);
+                beta = min(optimal_eval, beta);
+            }
+
+            if eval == optimal_eval {
+                optimal_move = m;
+            }
+
+            if alpha >= beta {
+                self.record_cutoff(m, depth);
+                break;
+            }
+        }
+
+        let bound = if optimal_eval <= alpha_0 {
+            Bound::Upper
+        } else if optimal_eval >= beta_0 {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        let stored_depth = if self.cut_off { depth } else { EXHAUSTED };
+        self.tt.store(Entry { hash, depth: stored_depth, bound, eval: optimal_eval, best_move: Some(optimal_move) });
+        self.cut_off |= outer_cut_off;
+
+        Some(optimal_eval)
+    }
+
+    // Sort the moves and the boards they lead to so that the most promising come first: the move
+    // of the transposition table, then corners, then killer moves, then the moves leaving the
+    // fewest moves to the opponent, ties being broken by the history of cutoffs.
+    fn order(&self, board: &Board, mut children: Vec<(Move, Board)>, tt_move: Option<Move>, depth: u8) -> Vec<(Move, Board)> {
+        if !self.move_ordering {
+            return children;
+        }
+
+        let opponent = board.player.map(|p| p.other());
+        let killers = self.killers[depth as usize];
+        children.sort_by_cached_key(|(m, child)| {
+            let class = if Some(*m) == tt_move {
+                0
+            } else if self.is_corner(m) {
+                1
+            } else if killers.contains(&Some(*m)) {
+                2
+            } else {
+                3
+            };
+            let mobility = if child.player == opponent { child.move_count() } else { 0 };
+            (class, mobility, Reverse(self.history[self.index(m)]))
+        });
+        children
+    }
+
+    fn record_cutoff(&mut self, m: Move, depth: u8) {
+        let i = self.index(&m);
+        self.history[i] += depth as u64 * depth as u64;
+
+        let killers = &mut self.killers[depth as usize];
+        if killers[0] != Some(m) {
+            killers[1] = killers[0];
+            killers[0] = Some(m);
+        }
+    }
+
+    fn is_corner(&self, m: &Move) -> bool {
+        (m.x() == 0 || m.x() == self.width - 1) && (m.y() == 0 || m.y() == self.height - 1)
+    }
+
+    fn index(&self, m: &Move) -> usize {
+        m.x() as usize + m.y() as usize * self.width as usize
+    }
 }
 
-fn ab_helper<T: BoundedOrd + Copy>(board: &Board, player: &Player, depth: u8, mut alpha: T, mut beta: T, end: Instant, heuristic: Heuristic<T>) -> T {
-    if depth == 0 || board.player.is_none() || Instant::now() >= end {
-        return heuristic(board, player);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Square;
+    use std::sync::atomic::AtomicU64;
+
+    #[test]
+    fn valid_move_under_time_pressure() {
+        let board = Board::new(8);
+        for timeout in [Duration::ZERO, Duration::from_millis(1), Duration::from_millis(50)] {
+            let m = ab_minmax(&board, timeout).unwrap();
+            assert!(board.is_valid_move(&m));
+        }
     }
 
-    let current_player = board.player.unwrap();
-    let maximize = current_player == *player;
+    #[test]
+    fn winning_move() {
+        let board = Board::new(4)
+            .set(2, 1, Square::Empty)
+            .set(1, 2, Square::Empty)
+            .set(2, 2, Square::Disc(Player::Black))
+            .set(2, 0, Square::Disc(Player::Black))
+            .set(1, 0, Square::Disc(Player::White));
+
+        let start = Instant::now();
+        let options = SearchOptions { timeout: Some(Duration::from_secs(10)), endgame_threshold: 0, ..Default::default() };
+        let m = search(&board, &options).best_move.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(10), "the search should stop once the game tree is exhausted");
+
+        let board = board.play(&m).unwrap();
+        assert_eq!(board.player, None);
+        assert_eq!(board.score(), (5, 0));
+    }
 
-    let mut optimal_eval = if maximize { T::MIN } else { T::MAX };
-    for m in board.moves() {
-        let eval = ab_helper(&board.play(&m).unwrap(), player, depth - 1, alpha, beta, end, heuristic);
+    #[test]
+    fn exhausted_tree() {
+        // Without limits, the 4x4 game is searched to its end, and no further.
+        let board = Board::new(4);
+        let options = SearchOptions { timeout: None, endgame_threshold: 0, ..Default::default() };
+        let (tt, stop) = (TranspositionTable::new(options.tt_size), AtomicBool::new(false));
+        let mut search = Search::new(&board, Player::Black, None, &DiscCount, &options, &tt, &stop);
+        let moves = search.root_moves(&board, Some(0));
+        let (_, score, depth) = search.deepen(&board, moves, &AtomicU8::new(1), None, EXHAUSTED - 1);
+        assert!(!search.cut_off);
+        assert!(depth <= board.empty_count());
+        assert_eq!(super::search(&board, &options).depth, depth);
+
+        // The last iteration finds the outcome of the game with perfect play.
+        let exact = solve(&board, EndgameMode::Exact, None).unwrap().score;
+        assert_eq!(score.unwrap().signum(), exact.signum());
+
+        // A depth limit cuts off leaves before the end.
+        let tt = TranspositionTable::new(options.tt_size);
+        let mut search = Search::new(&board, Player::Black, None, &DiscCount, &options, &tt, &stop);
+        let moves = search.root_moves(&board, Some(0));
+        assert_eq!(search.deepen(&board, moves, &AtomicU8::new(1), None, 4).2, 4);
+        assert!(search.cut_off);
+    }
 
-        if eval == optimal_eval {
-            continue;
+    #[test]
+    fn staggered_helpers() {
+        let board = middle_game();
+        let options = SearchOptions { timeout: None, ..Default::default() };
+        let stop = AtomicBool::new(false);
+        // Search fresh from the main thread's iteration, and return the completed depth and the
+        // number of nodes.
+        let helper = |iteration: u8, offset, last| {
+            let tt = TranspositionTable::new(options.tt_size);
+            let mut search = Search::new(&board, board.player.unwrap(), None, &DiscCount, &options, &tt, &stop);
+            let moves = search.root_moves(&board, Some(0));
+            let (_, _, depth) = search.deepen(&board, moves, &AtomicU8::new(iteration), offset, last);
+            (depth, search.nodes)
+        };
+
+        // While the main thread searches 3 plies, helpers start with 3 or 4 plies.
+        assert_eq!(helper(3, Some(0), 3).0, 3);
+        assert_eq!(helper(3, Some(1), 3).0, 0);
+        // Helpers a ply deeper skip the depth the main thread searches.
+        let (depth, nodes) = helper(3, Some(1), 4);
+        assert_eq!(depth, 4);
+        assert_eq!(nodes, helper(4, None, 4).1);
+        let (depth, more_nodes) = helper(3, Some(0), 4);
+        assert_eq!(depth, 4);
+        assert!(more_nodes > nodes);
+    }
+
+    // An 8x8 middle-game position, reached by always playing the middle available move.
+    fn middle_game() -> Board {
+        let mut board = Board::new(8);
+        for _ in 0..20 {
+            board = board.play(&board.moves()[board.move_count() as usize / 2]).unwrap();
         }
+        board
+    }
 
-        if maximize {
-            optimal_eval = max(eval, optimal_eval);
-            alpha = max(optimal_eval, alpha);
-        } else {
-            optimal_eval = min(eval, optimal_eval);
-            beta = min(optimal_eval, beta);
+    // Count the nodes visited by a reproducible search up to the given depth.
+    fn nodes(board: &Board, options: SearchOptions) -> u64 {
+        let report = search(board, &SearchOptions { timeout: None, seed: Some(0), ..options });
+        report.nodes
+    }
+
+    #[test]
+    fn other_shapes() {
+        for mut board in [Board::new(14), Board::rectangular(10, 8), Board::rectangular(4, 12)] {
+            for _ in 0..6 {
+                board = board.play(&board.moves()[board.move_count() as usize / 2]).unwrap();
+            }
+            let report = search(&board, &SearchOptions { timeout: None, depth: Some(4), ..Default::default() });
+            assert!(board.is_valid_move(&report.best_move.unwrap()));
+            assert_eq!(report.depth, 4);
         }
+    }
+
+    #[test]
+    fn transposition_table() {
+        let board = middle_game();
+        let without_tt = nodes(&board, SearchOptions { depth: Some(6), tt_size: 0, ..Default::default() });
+        let with_tt = nodes(&board, SearchOptions { depth: Some(6), ..Default::default() });
+        assert!(with_tt < without_tt, "{} nodes without transposition table, {} with", without_tt, with_tt);
+    }
+
+    #[test]
+    fn move_ordering() {
+        let board = middle_game();
+        let unordered = nodes(&board, SearchOptions { depth: Some(6), move_ordering: false, ..Default::default() });
+        let ordered = nodes(&board, SearchOptions { depth: Some(6), ..Default::default() });
+        assert!(ordered < unordered, "{} nodes without move ordering, {} with", unordered, ordered);
+    }
 
-        if alpha >= beta {
-            break;
+    #[test]
+    fn parallel_search() {
+        let board = middle_game();
+        let options = SearchOptions { timeout: None, depth: Some(5), seed: Some(3), ..Default::default() };
+        // A single thread is reproducible.
+        let single = search(&board, &options);
+        let again = search(&board, &options);
+        assert_eq!((single.best_move, single.score, single.nodes), (again.best_move, again.score, again.nodes));
+
+        let parallel = search(&board, &SearchOptions { threads: 4, ..options });
+        assert!(board.is_valid_move(&parallel.best_move.unwrap()));
+        assert_eq!(parallel.depth, 5);
+        assert!(parallel.score.is_some());
+
+        let timed = search(&board, &SearchOptions { timeout: Some(Duration::from_millis(50)), threads: 3, ..Default::default() });
+        assert!(board.is_valid_move(&timed.best_move.unwrap()));
+    }
+
+    #[test]
+    fn search_report() {
+        let board = Board::new(8);
+        let report = search(&board, &SearchOptions { timeout: None, depth: Some(3), ..Default::default() });
+        assert!(board.is_valid_move(&report.best_move.unwrap()));
+        assert_eq!(report.depth, 3);
+        assert!(report.nodes > 4);
+        let report = search(&board, &SearchOptions { timeout: None, depth: Some(1), ..Default::default() });
+        assert_eq!(report.score, Some(Score::Heuristic(3)));
+
+        // Below the endgame threshold, the score is exact.
+        let small = Board::new(4);
+        let solution = solve(&small, EndgameMode::Exact, None).unwrap();
+        assert_eq!(search(&small, &SearchOptions::default()).score, Some(Score::Exact(solution.score)));
+        // Unless the depth is limited to fewer plies than there are empty squares.
+        let limited = search(&small, &SearchOptions { depth: Some(2), ..Default::default() });
+        assert!(matches!(limited.score, Some(Score::Heuristic(_))));
+        assert_eq!(limited.depth, 2);
+        let deep = SearchOptions { depth: Some(small.empty_count()), threads: 2, ..Default::default() };
+        assert_eq!(search(&small, &deep).score, Some(Score::Exact(solution.score)));
+
+        let mut board = board;
+        board.player = None;
+        let report = search(&board, &SearchOptions::default());
+        assert_eq!(report.best_move, None);
+        assert_eq!(report.score, None);
+        assert!(report.pv.is_empty());
+    }
+
+    // Check that the variation starts with the best move and can be played.
+    fn assert_playable<S>(board: &Board, report: &SearchReport<S>) {
+        assert_eq!(report.pv.first().copied(), report.best_move);
+        let mut board = board.clone();
+        for m in &report.pv {
+            board = board.play(m).unwrap();
         }
     }
 
-    optimal_eval
-}
+    #[test]
+    fn principal_variation() {
+        let board = middle_game();
+        let report = search(&board, &SearchOptions { timeout: None, depth: Some(6), seed: Some(0), ..Default::default() });
+        assert_playable(&board, &report);
+        assert!(report.pv.len() > 1 && report.pv.len() <= 6, "{:?}", report.pv);
+        assert!(report.elapsed > Duration::ZERO && report.nps() > 0.0);
+
+        let small = Board::new(4);
+        let report = search(&small, &SearchOptions::default());
+        assert_playable(&small, &report);
+        assert!(report.pv.len() > 1);
+    }
 
-fn heuristic(board: &Board, player: &Player) -> i16 {
-    let (b, w) = board.score();
-    let rel_score: i16 = match player {
-        Player::Black => b as i16 - w as i16,
-        Player::White => w as i16 - b as i16,
-    };
+    #[test]
+    fn report_output() {
+        let report: SearchReport = SearchReport {
+            best_move: Some(Move::new(3, 2)),
+            score: Some(Score::Exact(-4)),
+            pv: vec!(Move::new(3, 2), Move::new(2, 2)),
+            depth: 12,
+            nodes: 3000,
+            elapsed: Duration::from_millis(1500),
+        };
+        assert_eq!(report.to_string(), "Best move D3, score -4 (exact), depth 12, PV D3 C3, 3000 nodes in 1.500 s (2000 nodes/s).");
+        assert_eq!(report.to_json(), "{\"move\":\"D3\",\"score\":-4,\"exact\":true,\"pv\":[\"D3\",\"C3\"],\"depth\":12,\"nodes\":3000,\"nps\":2000,\"elapsed\":1.500}");
+
+        let empty = SearchReport::<i32>::empty();
+        assert_eq!(empty.to_string(), "No move to search.");
+        assert_eq!(empty.to_json(), "{\"move\":null,\"score\":null,\"exact\":false,\"pv\":[],\"depth\":0,\"nodes\":0,\"nps\":0,\"elapsed\":0.000}");
+    }
+
+    // An evaluator with some state, counting how many positions it evaluates.
+    struct Counting(AtomicU64);
 
-    if board.player.is_none() {
-        match rel_score.cmp(&0) {
-            Ordering::Less => i16::MIN,
-            Ordering::Greater => i16::MAX,
-            Ordering::Equal => 0,
+    impl Evaluator for Counting {
+        type Score = i32;
+
+        fn evaluate(&self, board: &Board, player: &Player) -> i32 {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Mobility.evaluate(board, player)
         }
-    } else {
-        rel_score
+    }
+
+    #[test]
+    fn custom_evaluator() {
+        let board = middle_game();
+        let evaluator = Counting(AtomicU64::new(0));
+        let options = SearchOptions { timeout: None, depth: Some(3), ..Default::default() };
+        let report = search_with(&board, &options, &evaluator);
+        assert!(board.is_valid_move(&report.best_move.unwrap()));
+        assert!(evaluator.0.load(Ordering::Relaxed) > 0);
+
+        let evaluator = WeightedSum::new()
+            .add(1, DiscCount)
+            .add(5, Mobility)
+            .add(1, Corners::default())
+            .add(2, Frontier)
+            .add(10, Stability);
+        let report = search_with(&board, &options, &evaluator);
+        assert!(board.is_valid_move(&report.best_move.unwrap()));
     }
 }
@@ -0,0 +1,287 @@
+use super::tt::{Bound, Entry, TranspositionTable};
+use crate::board::{Board, Move, Player, Square};
+
+use std::{
+    cmp::{max, min},
+    time::Instant,
+};
+
+/// What the endgame solver computes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EndgameMode {
+    /// Only whether the game is won, lost or drawn: the score is 1, -1 or 0.
+    WinLossDraw,
+    /// The exact final disc difference.
+    Exact,
+}
+
+/// The result of solving a position exactly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    /// A move achieving the `score`, if the player to move has any.
+    pub best_move: Option<Move>,
+    /// The final disc difference with perfect play, from the point of view of the player to move,
+    /// or of `Black` if the game is over.
+    /// In `WinLossDraw` mode, only its sign is computed.
+    pub score: i32,
+    /// The number of nodes visited.
+    pub nodes: u64,
+}
+
+// Below this number of empty squares, sorting the moves costs more than it saves.
+const ORDERING_EMPTIES: u8 = 5;
+// Below this number of empty squares, using the transposition table costs more than it saves.
+const TT_EMPTIES: u8 = 7;
+const TT_SIZE: usize = 1 << 20;
+// Larger than any disc difference.
+const INFINITY: i32 = i16::MAX as i32;
+
+/// Solve the game from the given position, with both players playing perfectly.
+/// Return `None` if the deadline is reached first.
+pub fn solve(board: &Board, mode: EndgameMode, end: Option<Instant>) -> Option<Solution> {
+    let mut solver = Solver::new(board, end);
+    let (best_move, score) = solver.root(board, mode, |_, _| {})?;
+    Some(Solution { best_move, score, nodes: solver.nodes })
+}
+
+// Evaluations stored in the transposition table are from the point of view of the player to move.
+pub(super) struct Solver {
+    end: Option<Instant>,
+    width: u8,
+    height: u8,
+    pub(super) tt: TranspositionTable<i32>,
+    pub(super) nodes: u64,
+}
+
+impl Solver {
+    pub(super) fn new(board: &Board, end: Option<Instant>) -> Self {
+        let tt_size = if board.empty_count() >= TT_EMPTIES { TT_SIZE } else { 0 };
+        Self { end, width: board.width(), height: board.height(), tt: TranspositionTable::new(tt_size), nodes: 0 }
+    }
+
+    // Solve the root move by move, calling `progress` with the best move found so far once each
+    // move is solved, so that it can still be used if the deadline is reached.
+    pub(super) fn root(&mut self, board: &Board, mode: EndgameMode, mut progress: impl FnMut(Move, i32)) -> Option<(Option<Move>, i32)> {
+        let Some(player) = board.player else {
+            return Some((None, mode.score(final_score(board, Player::Black))));
+        };
+
+        let (mut alpha, beta) = match mode {
+            EndgameMode::WinLossDraw => (-1, 1),
+            EndgameMode::Exact => (-INFINITY, INFINITY),
+        };
+        let parity = self.parity(board);
+
+        let mut optimal = None;
+        for (m, child) in self.order(board, parity, None) {
+            let eval = self.child(&child, player, self.flip_parity(parity, &m), alpha, beta)?;
+            if optimal.is_none_or(|(_, e)| eval > e) {
+                optimal = Some((m, eval));
+                progress(m, eval);
+                alpha = max(eval, alpha);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let (m, eval) = optimal.expect("there is at least one move");
+        Some((Some(m), mode.score(eval)))
+    }
+
+    // Negamax with alpha-beta pruning, from the point of view of `player`.
+    // `player` is the player to move, or the last player to move if the game is over.
+    // Moves after the first are searched with a null window, and searched again only if they turn
+    // out to be better (principal variation search).
+    fn negamax(&mut self, board: &Board, player: Player, parity: u8, mut alpha: i32, mut beta: i32) -> Option<i32> {
+        if self.end.is_some_and(|end| Instant::now() >= end) {
+            return None;
+        }
+        self.nodes += 1;
+
+        if board.player.is_none() {
+            return Some(final_score(board, player));
+        }
+
+        let hash = board.hash();
+        let mut tt_move = None;
+        if let Some(entry) = self.tt.probe(hash) {
+            match entry.bound {
+                Bound::Exact => return Some(entry.eval),
+                Bound::Lower => alpha = max(entry.eval, alpha),
+                Bound::Upper => beta = min(entry.eval, beta),
+            }
+            if alpha >= beta {
+                return Some(entry.eval);
+            }
+            tt_move = entry.best_move;
+        }
+        let (alpha_0, beta_0) = (alpha, beta);
+
+        let mut optimal_eval = -INFINITY;
+        let mut optimal_move = None;
+        for (i, (m, child)) in self.order(board, parity, tt_move).into_iter().enumerate() {
+            let parity = self.flip_parity(parity, &m);
+            let eval = if i == 0 || beta - alpha <= 1 {
+                self.child(&child, player, parity, alpha, beta)?
+            } else {
+                let eval = self.child(&child, player, parity, alpha, alpha + 1)?;
+                if alpha < eval && eval < beta {
+                    self.child(&child, player, parity, eval, beta)?
+                } else {
+                    eval
+                }
+            };
+
+            if eval > optimal_eval {
+                optimal_eval = eval;
+                optimal_move = Some(m);
+            }
+            alpha = max(optimal_eval, alpha);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if optimal_eval <= alpha_0 {
+            Bound::Upper
+        } else if optimal_eval >= beta_0 {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        let depth = board.empty_count();
+        self.tt.store(Entry { hash, depth, bound, eval: optimal_eval, best_move: optimal_move });
+
+        Some(optimal_eval)
+    }
+
+    // Evaluate a child for `player`, who just played, taking into account that the opponent may
+    // have to pass.
+    fn child(&mut self, child: &Board, player: Player, parity: u8, alpha: i32, beta: i32) -> Option<i32> {
+        if child.player == Some(player) {
+            self.negamax(child, player, parity, alpha, beta)
+        } else {
+            self.negamax(child, player.other(), parity, -beta, -alpha).map(|e| -e)
+        }
+    }
+
+    // Play every move, searching first the move of the transposition table, then the moves leaving
+    // the fewest moves to the opponent (fastest-first), then those in a region with an odd number
+    // of empty squares (parity).
+    fn order(&self, board: &Board, parity: u8, tt_move: Option<Move>) -> Vec<(Move, Board)> {
+        let mut children: Vec<(Move, Board)> = board.moves().into_iter()
+            .map(|m| (m, board.play(&m).unwrap()))
+            .collect();
+
+        if board.empty_count() >= ORDERING_EMPTIES {
+            let opponent = board.player.map(|p| p.other());
+            children.sort_by_cached_key(|(m, child)| {
+                let mobility = if child.player == opponent { child.move_count() } else { 0 };
+                let even = parity & (1 << self.region(m)) == 0;
+                (Some(*m) != tt_move, mobility, even)
+            });
+        }
+        children
+    }
+
+    // The board is divided in 4 quadrants, whose parities of empty squares are stored in 4 bits.
+    fn region(&self, m: &Move) -> u8 {
+        (m.x() / (self.width / 2)) + 2 * (m.y() / (self.height / 2))
+    }
+
+    fn parity(&self, board: &Board) -> u8 {
+        let mut parity = 0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if board.get(x, y) == Square::Empty {
+                    parity ^= 1 << self.region(&Move::new(x, y));
+                }
+            }
+        }
+        parity
+    }
+
+    fn flip_parity(&self, parity: u8, m: &Move) -> u8 {
+        parity ^ (1 << self.region(m))
+    }
+}
+
+impl EndgameMode {
+    fn score(&self, eval: i32) -> i32 {
+        match self {
+            Self::WinLossDraw => eval.signum(),
+            Self::Exact => eval,
+        }
+    }
+}
+
+// The final disc difference from the point of view of `player`.
+fn final_score(board: &Board, player: Player) -> i32 {
+    let (b, w) = board.score();
+    match player {
+        Player::Black => b as i32 - w as i32,
+        Player::White => w as i32 - b as i32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Solve by exploring the whole game tree, without any pruning.
+    fn brute_force(board: &Board, player: Player) -> i32 {
+        match board.player {
+            None => final_score(board, player),
+            Some(p) => {
+                let evals = board.moves().into_iter().map(|m| brute_force(&board.play(&m).unwrap(), player));
+                if p == player { evals.max() } else { evals.min() }.unwrap()
+            }
+        }
+    }
+
+    // Reach a position with the given number of empty squares by always playing the middle
+    // available move.
+    fn position(width: u8, height: u8, empties: u8) -> Board {
+        let mut board = Board::rectangular(width, height);
+        while board.empty_count() > empties {
+            board = board.play(&board.moves()[board.move_count() as usize / 2]).unwrap();
+        }
+        board
+    }
+
+    #[test]
+    fn exact() {
+        for board in [Board::new(4), position(6, 6, 10), position(8, 8, 9), position(6, 4, 10), position(4, 14, 9)] {
+            let player = board.player.unwrap();
+            let solution = solve(&board, EndgameMode::Exact, None).unwrap();
+            assert_eq!(solution.score, brute_force(&board, player));
+            let after = board.play(&solution.best_move.unwrap()).unwrap();
+            assert_eq!(brute_force(&after, player), solution.score);
+        }
+    }
+
+    #[test]
+    fn win_loss_draw() {
+        for board in [Board::new(4), position(6, 6, 10), position(8, 8, 9), position(6, 4, 10), position(4, 14, 9)] {
+            let player = board.player.unwrap();
+            let solution = solve(&board, EndgameMode::WinLossDraw, None).unwrap();
+            assert_eq!(solution.score, brute_force(&board, player).signum());
+        }
+    }
+
+    #[test]
+    fn game_over() {
+        let mut board = Board::new(8);
+        board.player = None;
+        let solution = solve(&board, EndgameMode::Exact, None).unwrap();
+        assert_eq!(solution.best_move, None);
+        assert_eq!(solution.score, 0);
+    }
+
+    #[test]
+    fn deadline() {
+        let board = position(8, 8, 20);
+        assert_eq!(solve(&board, EndgameMode::Exact, Some(Instant::now())), None);
+    }
+}
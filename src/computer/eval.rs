@@ -0,0 +1,270 @@
+use super::{BoundedOrd, tt::Pack};
+use crate::board::{Board, Player, Square};
+
+/// A way of evaluating board states, for the search to compare them.
+/// Evaluators may carry state, like weights, tables or caches, which the threads of a search share.
+pub trait Evaluator {
+    type Score: BoundedOrd + Pack;
+
+    /// Evaluate a board state from the point of view of `player`: the higher, the better.
+    /// Finished games are also evaluated, and won games should be ranked above any other state.
+    fn evaluate(&self, board: &Board, player: &Player) -> Self::Score;
+}
+
+/// Evaluate by the difference of the number of discs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiscCount;
+
+/// Evaluate by the difference of the number of possible moves.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mobility;
+
+/// Evaluate by weighting the discs on corners, on X-squares (diagonally next to a corner) and on
+/// C-squares (orthogonally next to a corner).
+/// X-squares and C-squares are only weighted while their corner is empty.
+#[derive(Debug, Clone, Copy)]
+pub struct Corners {
+    pub corner: i32,
+    pub x_square: i32,
+    pub c_square: i32,
+}
+
+/// Evaluate by the difference of the number of frontier discs, that is discs next to an empty
+/// square, fewer being better.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Frontier;
+
+/// Evaluate by the difference of the number of stable discs, that can never be flipped.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stability;
+
+/// Evaluate by a weighted sum of other evaluations.
+#[derive(Default)]
+pub struct WeightedSum {
+    terms: Vec<(i32, Box<dyn Evaluator<Score = i32> + Send + Sync>)>,
+}
+
+impl Default for Corners {
+    fn default() -> Self {
+        Self { corner: 25, x_square: -12, c_square: -5 }
+    }
+}
+
+impl WeightedSum {
+    /// Create an empty sum, that evaluates everything to 0 except finished games.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an evaluator to the sum with the given weight.
+    pub fn add(mut self, weight: i32, evaluator: impl Evaluator<Score = i32> + Send + Sync + 'static) -> Self {
+        self.terms.push((weight, Box::new(evaluator)));
+        self
+    }
+}
+
+impl Evaluator for DiscCount {
+    type Score = i32;
+
+    fn evaluate(&self, board: &Board, player: &Player) -> i32 {
+        outcome(board, player).unwrap_or_else(|| disc_difference(board, player))
+    }
+}
+
+impl Evaluator for Mobility {
+    type Score = i32;
+
+    fn evaluate(&self, board: &Board, player: &Player) -> i32 {
+        outcome(board, player).unwrap_or_else(|| {
+            board.mobility(*player) as i32 - board.mobility(player.other()) as i32
+        })
+    }
+}
+
+impl Evaluator for Corners {
+    type Score = i32;
+
+    fn evaluate(&self, board: &Board, player: &Player) -> i32 {
+        if let Some(outcome) = outcome(board, player) {
+            return outcome;
+        }
+
+        let (right, bottom) = (board.width() - 1, board.height() - 1);
+        let mut eval = 0;
+        for (cx, cy, dx, dy) in [(0, 0, 1, 1), (right, 0, -1, 1), (0, bottom, 1, -1), (right, bottom, -1, -1)] {
+            let corner = board.get(cx, cy);
+            if corner != Square::Empty {
+                eval += self.corner * sign(corner, player);
+                continue;
+            }
+
+            let (x, y) = (cx.saturating_add_signed(dx), cy.saturating_add_signed(dy));
+            eval += self.x_square * sign(board.get(x, y), player);
+            eval += self.c_square * sign(board.get(x, cy), player);
+            eval += self.c_square * sign(board.get(cx, y), player);
+        }
+        eval
+    }
+}
+
+impl Evaluator for Frontier {
+    type Score = i32;
+
+    fn evaluate(&self, board: &Board, player: &Player) -> i32 {
+        outcome(board, player).unwrap_or_else(|| {
+            board.frontier(player.other()) as i32 - board.frontier(*player) as i32
+        })
+    }
+}
+
+impl Evaluator for Stability {
+    type Score = i32;
+
+    fn evaluate(&self, board: &Board, player: &Player) -> i32 {
+        outcome(board, player).unwrap_or_else(|| {
+            let (black, white) = board.stable_discs();
+            let (black, white) = (black.popcount() as i32, white.popcount() as i32);
+            match player {
+                Player::Black => black - white,
+                Player::White => white - black,
+            }
+        })
+    }
+}
+
+impl Evaluator for WeightedSum {
+    type Score = i32;
+
+    fn evaluate(&self, board: &Board, player: &Player) -> i32 {
+        // The sum saturates, short of the evaluations of finished games.
+        outcome(board, player).unwrap_or_else(|| {
+            self.terms.iter()
+                .fold(0i32, |sum, (w, e)| sum.saturating_add(w.saturating_mul(e.evaluate(board, player))))
+                .clamp(i32::MIN + 1, i32::MAX - 1)
+        })
+    }
+}
+
+impl BoundedOrd for i32 {
+    const MIN: i32 = i32::MIN;
+    const MAX: i32 = i32::MAX;
+}
+
+// If the game is over, return the extreme evaluation matching its outcome for `player`.
+pub(super) fn outcome(board: &Board, player: &Player) -> Option<i32> {
+    if board.player.is_some() {
+        return None;
+    }
+    Some(match disc_difference(board, player).signum() {
+        1 => i32::MAX,
+        -1 => i32::MIN,
+        _ => 0,
+    })
+}
+
+fn disc_difference(board: &Board, player: &Player) -> i32 {
+    let (b, w) = board.score();
+    match player {
+        Player::Black => b as i32 - w as i32,
+        Player::White => w as i32 - b as i32,
+    }
+}
+
+// Return 1 for a disc of `player`, -1 for a disc of the opponent and 0 for an empty square.
+fn sign(square: Square, player: &Player) -> i32 {
+    match square {
+        Square::Disc(p) if p == *player => 1,
+        Square::Disc(_) => -1,
+        Square::Empty => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A corner and part of its edges taken by black, and another corner taken by white.
+    //   A B C D
+    // 1 X X X O
+    // 2 X O _ _
+    // 3 X _ O _
+    // 4 _ _ _ _
+    fn position() -> Board {
+        let mut board = Board::new(4)
+            .set(2, 1, Square::Empty)
+            .set(1, 2, Square::Empty)
+            .set(2, 2, Square::Disc(Player::White))
+            .set(0, 0, Square::Disc(Player::Black))
+            .set(1, 0, Square::Disc(Player::Black))
+            .set(2, 0, Square::Disc(Player::Black))
+            .set(3, 0, Square::Disc(Player::White))
+            .set(0, 1, Square::Disc(Player::Black))
+            .set(0, 2, Square::Disc(Player::Black));
+        board.player = Some(Player::White);
+        board
+    }
+
+    #[test]
+    fn disc_count() {
+        assert_eq!(DiscCount.evaluate(&Board::new(8), &Player::Black), 0);
+        assert_eq!(DiscCount.evaluate(&position(), &Player::Black), 2);
+        assert_eq!(DiscCount.evaluate(&position(), &Player::White), -2);
+    }
+
+    #[test]
+    fn mobility() {
+        let board = position();
+        let eval = board.mobility(Player::Black) as i32 - board.mobility(Player::White) as i32;
+        assert_eq!(Mobility.evaluate(&board, &Player::Black), eval);
+        assert_eq!(Mobility.evaluate(&Board::new(8), &Player::White), 0);
+    }
+
+    #[test]
+    fn corners() {
+        let corners = Corners { corner: 10, x_square: -3, c_square: -1 };
+        assert_eq!(corners.evaluate(&Board::new(8), &Player::Black), 0);
+        // One corner each, a black C-square and a white X-square next to empty corners.
+        assert_eq!(corners.evaluate(&position(), &Player::Black), 10 - 10 - 1 + 3);
+    }
+
+    #[test]
+    fn frontier() {
+        let board = position();
+        assert_eq!(Frontier.evaluate(&board, &Player::Black), board.frontier(Player::White) as i32 - board.frontier(Player::Black) as i32);
+        assert_eq!(board.frontier(Player::Black), 4);
+        assert_eq!(board.frontier(Player::White), 3);
+    }
+
+    #[test]
+    fn stability() {
+        assert_eq!(Stability.evaluate(&Board::new(8), &Player::Black), 0);
+        assert_eq!(Stability.evaluate(&position(), &Player::Black), 5 - 1);
+    }
+
+    #[test]
+    fn weighted_sum() {
+        let board = position();
+        let sum = WeightedSum::new()
+            .add(2, DiscCount)
+            .add(-1, Stability);
+        assert_eq!(sum.evaluate(&board, &Player::Black), 2 * 2 - 4);
+        assert_eq!(WeightedSum::new().evaluate(&board, &Player::Black), 0);
+
+        // Extreme weights saturate without reaching the evaluations of finished games.
+        let huge = WeightedSum::new().add(i32::MAX, DiscCount).add(i32::MAX, DiscCount);
+        assert_eq!(huge.evaluate(&board, &Player::Black), i32::MAX - 1);
+        assert_eq!(huge.evaluate(&board, &Player::White), i32::MIN + 1);
+        let nested = WeightedSum::new().add(i32::MIN, huge).add(-1, DiscCount);
+        assert_eq!(nested.evaluate(&board, &Player::Black), i32::MIN + 1);
+    }
+
+    #[test]
+    fn finished_games() {
+        let mut board = position();
+        board.player = None;
+        let sum = WeightedSum::new().add(1, Mobility).add(1, Frontier);
+        assert_eq!(sum.evaluate(&board, &Player::Black), i32::MAX);
+        assert_eq!(sum.evaluate(&board, &Player::White), i32::MIN);
+        assert_eq!(Corners::default().evaluate(&board, &Player::White), i32::MIN);
+    }
+}
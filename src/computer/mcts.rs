@@ -0,0 +1,352 @@
+use super::{Score, SearchReport};
+use crate::board::{Board, Move, Player, Square};
+
+use std::time::{Duration, Instant};
+use rand::{SeedableRng, rng, rngs::StdRng, seq::{IndexedRandom, SliceRandom}};
+
+// How many plies below the root a position is looked for to reuse the tree.
+const REUSE_DEPTH: usize = 3;
+
+/// How moves are chosen during the random games of a Monte Carlo search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Playout {
+    /// Uniformly at random.
+    Random,
+    /// At random, but taking corners when possible and avoiding X-squares (diagonally next to an
+    /// empty corner) when there are other moves.
+    Light,
+}
+
+/// The settings of a Monte Carlo tree search.
+#[derive(Debug, Clone)]
+pub struct MctsOptions {
+    /// The weight of exploration against exploitation in the UCT formula.
+    pub exploration: f64,
+    pub playout: Playout,
+    /// The maximum number of iterations of a search, if any.
+    pub iterations: Option<u64>,
+    /// The seed of the random choices, for reproducible searches.
+    pub seed: Option<u64>,
+}
+
+impl Default for MctsOptions {
+    fn default() -> Self {
+        Self {
+            exploration: std::f64::consts::SQRT_2,
+            playout: Playout::Light,
+            iterations: None,
+            seed: None,
+        }
+    }
+}
+
+/// A Monte Carlo tree search, selecting moves with UCT and evaluating positions by the outcome of
+/// random games.
+/// The tree is kept between searches, and reused when the next position was already explored.
+pub struct Mcts {
+    options: MctsOptions,
+    rng: StdRng,
+    // The nodes of the tree, the root being the first one if the tree is not empty.
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    board: Board,
+    // The move leading to the node from its parent.
+    m: Option<Move>,
+    // The player who played that move, from whose point of view `wins` are counted.
+    mover: Option<Player>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    visits: u32,
+    // Wins count as 1 and draws as 0.5.
+    wins: f64,
+}
+
+impl Mcts {
+    pub fn new(options: MctsOptions) -> Self {
+        let rng = match options.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_rng(&mut rng()),
+        };
+        Self { options, rng, nodes: vec!() }
+    }
+
+    /// Search the position until the timeout or the maximum number of iterations, and return the
+    /// most visited move.
+    /// The score is the rate of wins found for that move, the depth that of the deepest node
+    /// selected, and the nodes the number of iterations.
+    pub fn search(&mut self, board: &Board, timeout: Option<Duration>) -> SearchReport<f64> {
+        if board.player.is_none() {
+            self.nodes.clear();
+            return SearchReport::empty();
+        }
+        let start = Instant::now();
+        let end = timeout.map(|t| start + t);
+        self.reroot(board);
+
+        let mut iterations = 0;
+        let mut depth = 0;
+        while self.options.iterations.is_none_or(|n| iterations < n) && end.is_none_or(|end| Instant::now() < end) {
+            depth = depth.max(self.iterate());
+            iterations += 1;
+        }
+
+        let best = self.nodes[0].children.iter()
+            .max_by_key(|&&c| self.nodes[c].visits)
+            .map(|&c| &self.nodes[c]);
+        SearchReport {
+            best_move: best.and_then(|n| n.m).or_else(|| board.moves().first().copied()),
+            score: best.filter(|n| n.visits > 0).map(|n| Score::Heuristic(n.wins / n.visits as f64)),
+            pv: self.principal_variation(),
+            depth: depth.min(u8::MAX as usize) as u8,
+            nodes: iterations,
+            elapsed: start.elapsed(),
+        }
+    }
+
+    // Follow the most visited children from the root.
+    fn principal_variation(&self) -> Vec<Move> {
+        let mut pv = vec!();
+        let mut node = &self.nodes[0];
+        while let Some(&child) = node.children.iter().max_by_key(|&&c| self.nodes[c].visits) {
+            node = &self.nodes[child];
+            pv.extend(node.m);
+        }
+        pv
+    }
+
+    // Make the node of the board the root, keeping its subtree if it is in the tree.
+    fn reroot(&mut self, board: &Board) {
+        let mut level = if self.nodes.is_empty() { vec!() } else { vec!(0) };
+        for _ in 0..REUSE_DEPTH {
+            if let Some(&found) = level.iter().find(|&&i| self.nodes[i].board == *board) {
+                self.keep_subtree(found);
+                return;
+            }
+            level = level.iter().flat_map(|&i| self.nodes[i].children.clone()).collect();
+        }
+
+        let mut untried = board.moves();
+        untried.shuffle(&mut self.rng);
+        self.nodes = vec!(Node {
+            board: board.clone(),
+            m: None,
+            mover: None,
+            parent: None,
+            children: vec!(),
+            untried,
+            visits: 0,
+            wins: 0.0,
+        });
+    }
+
+    fn keep_subtree(&mut self, root: usize) {
+        let mut old: Vec<Option<Node>> = std::mem::take(&mut self.nodes).into_iter().map(Some).collect();
+        // Copy the subtree breadth first, so that children are renumbered after their parent.
+        let mut queue = std::collections::VecDeque::from([(root, None)]);
+        while let Some((i, parent)) = queue.pop_front() {
+            let mut node = old[i].take().expect("nodes have a single parent");
+            let index = self.nodes.len();
+            for &child in &node.children {
+                queue.push_back((child, Some(index)));
+            }
+            node.children.clear();
+            node.parent = parent;
+            if let Some(parent) = parent {
+                self.nodes[parent].children.push(index);
+            }
+            self.nodes.push(node);
+        }
+    }
+
+    // Select a leaf, expand it, play a random game from it and update the statistics on the way.
+    // Return the depth of the node expanded.
+    fn iterate(&mut self) -> usize {
+        let mut i = 0;
+        let mut depth = 0;
+        while self.nodes[i].untried.is_empty() && !self.nodes[i].children.is_empty() {
+            i = self.select(i);
+            depth += 1;
+        }
+
+        if let Some(m) = self.nodes[i].untried.pop() {
+            let board = self.nodes[i].board.play(&m).expect("untried moves are valid");
+            let mut untried = board.moves();
+            untried.shuffle(&mut self.rng);
+            let child = Node {
+                mover: self.nodes[i].board.player,
+                board,
+                m: Some(m),
+                parent: Some(i),
+                children: vec!(),
+                untried,
+                visits: 0,
+                wins: 0.0,
+            };
+            self.nodes.push(child);
+            let index = self.nodes.len() - 1;
+            self.nodes[i].children.push(index);
+            i = index;
+            depth += 1;
+        }
+
+        let (black, white) = self.playout(self.nodes[i].board.clone()).score();
+        let mut node = Some(i);
+        while let Some(j) = node {
+            let n = &mut self.nodes[j];
+            n.visits += 1;
+            n.wins += match (n.mover, black.cmp(&white)) {
+                (None, _) => 0.0,
+                (_, std::cmp::Ordering::Equal) => 0.5,
+                (Some(Player::Black), std::cmp::Ordering::Greater) | (Some(Player::White), std::cmp::Ordering::Less) => 1.0,
+                _ => 0.0,
+            };
+            node = n.parent;
+        }
+        depth
+    }
+
+    // Return the child with the highest upper confidence bound.
+    fn select(&self, i: usize) -> usize {
+        let log_visits = (self.nodes[i].visits.max(1) as f64).ln();
+        let uct = |c: usize| {
+            let child = &self.nodes[c];
+            let visits = child.visits.max(1) as f64;
+            child.wins / visits + self.options.exploration * (log_visits / visits).sqrt()
+        };
+        *self.nodes[i].children.iter()
+            .max_by(|&&a, &&b| uct(a).total_cmp(&uct(b)))
+            .expect("the node has children")
+    }
+
+    // Play until the end of the game and return the final board.
+    fn playout(&mut self, mut board: Board) -> Board {
+        while board.player.is_some() {
+            let moves = board.moves();
+            let m = match self.options.playout {
+                Playout::Random => *moves.choose(&mut self.rng).expect("the player to move has moves"),
+                Playout::Light => light(&board, &moves, &mut self.rng),
+            };
+            board = board.play(&m).expect("moves are valid");
+        }
+        board
+    }
+}
+
+// Take a corner if possible, else a random move that is not an X-square next to an empty corner,
+// else any random move.
+fn light(board: &Board, moves: &[Move], rng: &mut impl rand::Rng) -> Move {
+    let (right, bottom) = (board.width() - 1, board.height() - 1);
+    let corner = |i: u8, last: u8| if i == 0 || i == 1 { 0 } else { last };
+    let is_corner = |m: &&Move| (m.x() == 0 || m.x() == right) && (m.y() == 0 || m.y() == bottom);
+    let is_x_square = |m: &&Move| {
+        (m.x() == 1 || m.x() == right - 1) && (m.y() == 1 || m.y() == bottom - 1)
+            && board.get(corner(m.x(), right), corner(m.y(), bottom)) == Square::Empty
+    };
+
+    let corners: Vec<&Move> = moves.iter().filter(is_corner).collect();
+    if let Some(m) = corners.choose(rng) {
+        return **m;
+    }
+    let others: Vec<&Move> = moves.iter().filter(|m| !is_x_square(m)).collect();
+    match others.choose(rng) {
+        Some(m) => **m,
+        None => *moves.choose(rng).expect("the player to move has moves"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::MAX_SIZE;
+
+    fn seeded(iterations: u64) -> Mcts {
+        Mcts::new(MctsOptions { iterations: Some(iterations), seed: Some(1), ..Default::default() })
+    }
+
+    #[test]
+    fn every_size() {
+        let boards = (4..=MAX_SIZE).step_by(2).map(Board::new).chain([Board::rectangular(8, 10), Board::rectangular(14, 6)]);
+        for board in boards {
+            let mut mcts = Mcts::new(MctsOptions::default());
+            let report = mcts.search(&board, Some(Duration::from_millis(20)));
+            assert!(board.is_valid_move(&report.best_move.unwrap()));
+            assert!(report.nodes > 0);
+        }
+        let mut board = Board::new(4);
+        board.player = None;
+        assert_eq!(Mcts::new(MctsOptions::default()).search(&board, None).best_move, None);
+    }
+
+    #[test]
+    fn winning_move() {
+        // Black wins with all discs by playing C1.
+        let board = Board::new(4)
+            .set(2, 1, Square::Empty)
+            .set(1, 2, Square::Empty)
+            .set(2, 2, Square::Disc(Player::Black))
+            .set(2, 0, Square::Disc(Player::Black))
+            .set(1, 0, Square::Disc(Player::White));
+        for playout in [Playout::Random, Playout::Light] {
+            let mut mcts = Mcts::new(MctsOptions { playout, iterations: Some(2000), seed: Some(1), ..Default::default() });
+            let report = mcts.search(&board, None);
+            let after = board.play(&report.best_move.unwrap()).unwrap();
+            assert_eq!(after.player, None);
+            assert_eq!(after.score(), (5, 0));
+            assert!(matches!(report.score, Some(Score::Heuristic(rate)) if rate > 0.9));
+        }
+    }
+
+    #[test]
+    fn reproducible() {
+        let board = Board::new(6);
+        let first = seeded(500).search(&board, None);
+        let second = seeded(500).search(&board, None);
+        assert_eq!(first.best_move, second.best_move);
+        assert_eq!(first.score, second.score);
+        assert_eq!(first.nodes, 500);
+        assert_eq!(first.pv.first().copied(), first.best_move);
+        assert!(first.pv.len() > 1);
+    }
+
+    #[test]
+    fn tree_reuse() {
+        let mut mcts = seeded(1000);
+        let board = Board::new(6);
+        let m = mcts.search(&board, None).best_move.unwrap();
+        let board = board.play(&m).unwrap();
+        let reply = board.moves()[0];
+        let board = board.play(&reply).unwrap();
+
+        // The subtree of the position is kept, with the statistics of its root.
+        mcts.reroot(&board);
+        let visits = mcts.nodes[0].visits;
+        assert!(visits > 0);
+        assert_eq!(mcts.nodes.len() as u32, subtree_size(&mcts, 0));
+        assert!(mcts.nodes.iter().enumerate().all(|(i, n)| n.children.iter().all(|&c| mcts.nodes[c].parent == Some(i))));
+        mcts.search(&board, None);
+        assert_eq!(mcts.nodes[0].visits, visits + 1000);
+
+        // Unknown positions start a new tree.
+        mcts.reroot(&Board::new(4));
+        assert_eq!((mcts.nodes.len(), mcts.nodes[0].visits), (1, 0));
+    }
+
+    fn subtree_size(mcts: &Mcts, i: usize) -> u32 {
+        1 + mcts.nodes[i].children.iter().map(|&c| subtree_size(mcts, c)).sum::<u32>()
+    }
+
+    #[test]
+    fn light_playouts() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let board = Board::new(8);
+        let (corner, x_square, other) = (Move::new(0, 0), Move::new(1, 1), Move::new(3, 2));
+        for _ in 0..20 {
+            assert_eq!(light(&board, &[x_square, corner, other], &mut rng), corner);
+            assert_eq!(light(&board, &[x_square, other], &mut rng), other);
+            assert_eq!(light(&board, &[x_square], &mut rng), x_square);
+        }
+    }
+}
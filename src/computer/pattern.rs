@@ -0,0 +1,388 @@
+use super::eval::{Evaluator, outcome};
+use crate::board::{Board, ParsingError, Player, Square, Transform};
+
+use std::{fmt, fs, path::Path};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+// A weights file starts with this tag, the size of the board and the number of phases, followed
+// by the weights as little-endian 32-bit floats.
+const MAGIC: &[u8; 4] = b"RVPW";
+const HEADER_SIZE: usize = 6;
+// The weights of the longest patterns grow as 3 to the power of the size of the board, so weights
+// are limited to square boards of size at most 10.
+const MAX_SIZE: u8 = 10;
+
+/// Evaluate by summing weights looked up from the contents of groups of squares, called patterns:
+/// edges with their X-squares, 3x3 and 2x5 corners, lines parallel to the edges and diagonals.
+/// Each pattern is looked up in all its orientations, which share their weights, and weights
+/// depend on the phase of the game, given by the number of discs on the board.
+/// Evaluations are in hundredths of discs, and weights are fitted with `train`.
+#[derive(Clone, PartialEq)]
+pub struct Patterns {
+    size: u8,
+    phases: u8,
+    // Every orientation of every pattern, as the index of its first weight within a phase and its
+    // squares, given by `x + y * size`.
+    instances: Vec<(usize, Vec<usize>)>,
+    // The number of weights of a phase.
+    stride: usize,
+    weights: Vec<f32>,
+}
+
+/// A position with the evaluation, in discs, the patterns are trained towards.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Example {
+    pub board: Board,
+    /// The player the evaluation is from the point of view of.
+    pub player: Player,
+    pub target: f32,
+}
+
+/// The settings of the training of `Patterns`.
+#[derive(Debug, Clone)]
+pub struct Training {
+    pub phases: u8,
+    pub epochs: usize,
+    pub learning_rate: f32,
+    /// The fraction of the samples kept aside to measure the error on positions not trained on.
+    pub validation: f64,
+    pub seed: u64,
+}
+
+impl Patterns {
+    /// Create an evaluator for the given board size, with all weights at 0.
+    pub fn new(size: u8, phases: u8) -> Self {
+        let mut instances = vec!();
+        let mut stride = 0;
+        for pattern in patterns(size) {
+            let mut seen = vec!();
+            for transform in Transform::ALL {
+                let squares: Vec<usize> = pattern.iter().map(|&(x, y)| {
+                    let (x, y) = transform.apply(size, size, x, y);
+                    x as usize + y as usize * size as usize
+                }).collect();
+                // Orientations reading the same squares in another order are kept, so that the
+                // evaluation is the same for symmetric positions.
+                if !seen.contains(&squares) {
+                    seen.push(squares.clone());
+                    instances.push((stride, squares));
+                }
+            }
+            stride += 3usize.pow(pattern.len() as u32);
+        }
+        let phases = phases.max(1);
+        Self { size, phases, instances, stride, weights: vec![0.0; stride * phases as usize] }
+    }
+
+    /// Return the size of the boards the evaluator is made for.
+    pub fn size(&self) -> u8 {
+        self.size
+    }
+
+    /// Load weights written by `save`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ParsingError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Write the weights to a file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ParsingError> {
+        Ok(fs::write(path, self.to_bytes())?)
+    }
+
+    /// Read weights from their binary representation.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParsingError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(ParsingError::EmptyFile);
+        }
+        if &bytes[..4] != MAGIC {
+            return Err(ParsingError::Generic);
+        }
+        let (size, phases) = (bytes[4], bytes[5]);
+        if !is_valid_size(size) || phases == 0 {
+            return Err(ParsingError::BadSize);
+        }
+        let mut patterns = Self::new(size, phases);
+        let bytes = &bytes[HEADER_SIZE..];
+        if bytes.len() != 4 * patterns.weights.len() {
+            return Err(ParsingError::InconsistentSize);
+        }
+        for (weight, chunk) in patterns.weights.iter_mut().zip(bytes.chunks(4)) {
+            *weight = f32::from_le_bytes(chunk.try_into().expect("chunks of 4 bytes"));
+        }
+        Ok(patterns)
+    }
+
+    /// Return the binary representation of the weights.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend([self.size, self.phases]);
+        bytes.extend(self.weights.iter().flat_map(|w| w.to_le_bytes()));
+        bytes
+    }
+
+    /// Run an epoch of stochastic gradient descent over the examples, in a random order, fitting
+    /// the evaluation of each position to its target.
+    /// Return the root mean square error of the evaluations, in discs, met during the epoch.
+    pub fn train(&mut self, examples: &[Example], learning_rate: f32, rng: &mut impl Rng) -> Result<f64, ParsingError> {
+        if examples.iter().any(|e| (e.board.width(), e.board.height()) != (self.size, self.size)) {
+            return Err(ParsingError::InconsistentSize);
+        }
+        let mut order: Vec<usize> = (0..examples.len()).collect();
+        order.shuffle(rng);
+
+        let mut squared_error = 0.0;
+        for i in order {
+            let example = &examples[i];
+            let features = self.features(&example.board, &example.player);
+            let error = example.target - features.iter().map(|&f| self.weights[f]).sum::<f32>();
+            squared_error += error as f64 * error as f64;
+            // The step is shared between the features, so that it does not depend on their number.
+            let step = learning_rate * error / features.len().max(1) as f32;
+            for f in features {
+                self.weights[f] += step;
+            }
+        }
+        Ok((squared_error / examples.len().max(1) as f64).sqrt())
+    }
+
+    /// Fit new weights to the examples, which must all be on square boards of the same size, at
+    /// most 10, as for `from_bytes`.
+    /// `progress` is called after every epoch with its number, its error and the validation error,
+    /// if any examples are kept aside.
+    pub fn fit(examples: &[Example], training: &Training, mut progress: impl FnMut(usize, f64, Option<f64>)) -> Result<Self, ParsingError> {
+        let board = &examples.first().ok_or(ParsingError::EmptyFile)?.board;
+        let size = board.width();
+        if !is_valid_size(size) || board.height() != size {
+            return Err(ParsingError::BadSize);
+        }
+        let mut rng = StdRng::seed_from_u64(training.seed);
+        let mut examples = examples.to_vec();
+        examples.shuffle(&mut rng);
+        let split = examples.len() - (examples.len() as f64 * training.validation) as usize;
+        let (examples, validation) = examples.split_at(split);
+
+        let mut patterns = Self::new(size, training.phases);
+        for epoch in 1..=training.epochs {
+            let error = patterns.train(examples, training.learning_rate, &mut rng)?;
+            progress(epoch, error, (!validation.is_empty()).then(|| patterns.error(validation)));
+        }
+        Ok(patterns)
+    }
+
+    /// Return the root mean square error of the evaluations of the examples, in discs.
+    pub fn error(&self, examples: &[Example]) -> f64 {
+        let squared_error: f64 = examples.iter().map(|example| {
+            let error = (example.target - self.value(&example.board, &example.player)) as f64;
+            error * error
+        }).sum();
+        (squared_error / examples.len().max(1) as f64).sqrt()
+    }
+
+    // Return the evaluation in discs, before rounding.
+    fn value(&self, board: &Board, player: &Player) -> f32 {
+        self.features(board, player).iter().map(|&f| self.weights[f]).sum()
+    }
+
+    // Return the index of the weight of every pattern instance for the position.
+    fn features(&self, board: &Board, player: &Player) -> Vec<usize> {
+        let size = self.size;
+        let mut cells = Vec::with_capacity(size as usize * size as usize);
+        for y in 0..size {
+            for x in 0..size {
+                cells.push(match board.get(x, y) {
+                    Square::Empty => 0,
+                    Square::Disc(p) if p == *player => 1,
+                    Square::Disc(_) => 2,
+                });
+            }
+        }
+
+        let squares = size as usize * size as usize;
+        let placed = (squares - board.empty_count() as usize).saturating_sub(4);
+        let phase = (placed * self.phases as usize / (squares - 3)).min(self.phases as usize - 1);
+        self.instances.iter().map(|(offset, instance)| {
+            let index = instance.iter().fold(0, |index, &square| 3 * index + cells[square]);
+            phase * self.stride + offset + index
+        }).collect()
+    }
+}
+
+impl Evaluator for Patterns {
+    type Score = i32;
+
+    // Boards of another shape than the evaluator's are evaluated to 0.
+    fn evaluate(&self, board: &Board, player: &Player) -> i32 {
+        outcome(board, player).unwrap_or_else(|| {
+            if (board.width(), board.height()) != (self.size, self.size) {
+                return 0;
+            }
+            (100.0 * self.value(board, player)).round() as i32
+        })
+    }
+}
+
+// The weights are left out, as there are too many of them.
+impl fmt::Debug for Patterns {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Patterns")
+            .field("size", &self.size)
+            .field("phases", &self.phases)
+            .field("instances", &self.instances.len())
+            .field("weights", &self.weights.len())
+            .finish()
+    }
+}
+
+fn is_valid_size(size: u8) -> bool {
+    (2..=MAX_SIZE).contains(&size)
+}
+
+// Return the squares of each pattern in one of its orientations, leaving out those that do not
+// fit on the board.
+fn patterns(size: u8) -> Vec<Vec<(u8, u8)>> {
+    if size < 4 {
+        return vec!();
+    }
+    let last = size - 1;
+    let mut patterns = vec!();
+
+    // An edge with its two X-squares.
+    let mut edge: Vec<(u8, u8)> = (0..size).map(|x| (x, 0)).collect();
+    edge.extend([(1, 1), (last - 1, 1)]);
+    patterns.push(edge);
+
+    patterns.push((0..3).flat_map(|y| (0..3).map(move |x| (x, y))).collect());
+    if size >= 6 {
+        patterns.push((0..2).flat_map(|y| (0..5).map(move |x| (x, y))).collect());
+    }
+
+    // Lines parallel to the edges, up to the middle of the board.
+    for y in 1..size / 2 {
+        patterns.push((0..size).map(|x| (x, y)).collect());
+    }
+
+    // Diagonals of at least 4 squares.
+    for offset in 0..=size - 4 {
+        patterns.push((0..size - offset).map(|i| (i + offset, i)).collect());
+    }
+    patterns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::DiscCount;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    // Play the moves with the given indices among the moves available.
+    fn position(size: u8, choices: &[usize]) -> Board {
+        let mut board = Board::new(size);
+        for &i in choices {
+            let moves = board.moves();
+            board = board.play(&moves[i % moves.len()]).unwrap();
+        }
+        board
+    }
+
+    #[test]
+    fn instances() {
+        let patterns = Patterns::new(8, 1);
+        // 8 orientations of every pattern, but for the main diagonals which have 4.
+        assert_eq!(patterns.instances.len(), 8 * 3 + 8 * 3 + 4 + 8 * 4);
+        assert_eq!(Patterns::new(2, 1).instances.len(), 0);
+        for size in [4, 6, 10] {
+            let patterns = Patterns::new(size, 2);
+            assert!(patterns.instances.iter().all(|(_, squares)| squares.iter().all(|&s| s < size as usize * size as usize)));
+        }
+    }
+
+    #[test]
+    fn symmetries() {
+        // Weights shared between orientations give the same evaluation to symmetric positions.
+        let mut patterns = Patterns::new(6, 2);
+        let mut rng = StdRng::seed_from_u64(0);
+        for weight in patterns.weights.iter_mut() {
+            *weight = rng.random_range(-1.0..1.0);
+        }
+        // The moves of the starting position are symmetric to each other.
+        let board = Board::new(6);
+        let evals: Vec<i32> = board.moves().iter()
+            .map(|m| patterns.evaluate(&board.play(m).unwrap(), &Player::Black))
+            .collect();
+        assert!(evals.iter().all(|&e| e == evals[0]), "{:?}", evals);
+    }
+
+    #[test]
+    fn files() {
+        let mut patterns = Patterns::new(6, 3);
+        patterns.weights[5] = 1.5;
+        patterns.weights[patterns.stride * 2] = -2.0;
+        let bytes = patterns.to_bytes();
+        assert_eq!(Patterns::from_bytes(&bytes), Ok(patterns.clone()));
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("weights.bin");
+        patterns.save(&path).unwrap();
+        assert_eq!(Patterns::load(&path), Ok(patterns));
+
+        assert_eq!(Patterns::from_bytes(b"RVP"), Err(ParsingError::EmptyFile));
+        assert_eq!(Patterns::from_bytes(b"ABCD\x06\x03"), Err(ParsingError::Generic));
+        assert_eq!(Patterns::from_bytes(b"RVPW\x0b\x03"), Err(ParsingError::BadSize));
+        assert_eq!(Patterns::from_bytes(&bytes[..bytes.len() - 4]), Err(ParsingError::InconsistentSize));
+    }
+
+    #[test]
+    fn training() {
+        // Label positions with an evaluation the patterns can express: the corners held.
+        let corners = |board: &Board, player: &Player| -> i32 {
+            [(0, 0), (5, 0), (0, 5), (5, 5)].iter().map(|&(x, y)| match board.get(x, y) {
+                Square::Disc(p) if p == *player => 4,
+                Square::Disc(_) => -4,
+                Square::Empty => 0,
+            }).sum()
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut examples = vec!();
+        while examples.len() < 500 {
+            let choices: Vec<usize> = (0..rng.random_range(10..28)).map(|_| rng.random::<u32>() as usize).collect();
+            let board = position(6, &choices);
+            if let Some(player) = board.player {
+                let target = corners(&board, &player) as f32;
+                examples.push(Example { board, player, target });
+            }
+        }
+
+        let mut patterns = Patterns::new(6, 2);
+        let initial = patterns.error(&examples);
+        for _ in 0..20 {
+            patterns.train(&examples, 0.5, &mut rng).unwrap();
+        }
+        let error = patterns.error(&examples);
+        assert!(error < initial / 4.0, "{} should be well below {}", error, initial);
+
+        let small = [Example { board: Board::new(4), player: Player::Black, target: 0.0 }];
+        assert_eq!(patterns.train(&small, 0.5, &mut rng), Err(ParsingError::InconsistentSize));
+
+        let training = Training { phases: 2, epochs: 20, learning_rate: 0.5, validation: 0.2, seed: 0 };
+        let mut errors = vec!();
+        let fitted = Patterns::fit(&examples, &training, |epoch, error, validation| errors.push((epoch, error, validation))).unwrap();
+        assert!(fitted.error(&examples) < initial / 2.0, "{} should be below {}", fitted.error(&examples), initial);
+        assert_eq!(errors.len(), 20);
+        assert!(errors.iter().all(|&(_, error, validation)| error.is_finite() && validation.is_some()));
+        assert_eq!(Patterns::fit(&[], &training, |_, _, _| { }), Err(ParsingError::EmptyFile));
+        for board in [Board::new(12), Board::rectangular(6, 8)] {
+            let example = Example { board, player: Player::Black, target: 0.0 };
+            assert_eq!(Patterns::fit(&[example], &training, |_, _, _| { }), Err(ParsingError::BadSize));
+        }
+    }
+
+    #[test]
+    fn evaluation() {
+        let mut board = position(8, &[3, 1, 4, 1, 5]);
+        let patterns = Patterns::new(8, 4);
+        assert_eq!(patterns.evaluate(&board, &Player::Black), 0);
+        assert_eq!(patterns.evaluate(&Board::new(6), &Player::Black), 0);
+        board.player = None;
+        assert_eq!(patterns.evaluate(&board, &Player::Black), DiscCount.evaluate(&board, &Player::Black));
+    }
+}
@@ -0,0 +1,210 @@
+use crate::board::Move;
+
+use std::{
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// The kind of information an evaluation stored in the table gives about the real value of a
+/// position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+/// The result of searching a position, as stored in the `TranspositionTable`.
+#[derive(Debug, Clone, Copy)]
+pub struct Entry<T> {
+    pub hash: u64,
+    pub depth: u8,
+    pub bound: Bound,
+    pub eval: T,
+    pub best_move: Option<Move>,
+}
+
+/// An evaluation that fits in 32 bits, so that it can be stored in the `TranspositionTable`.
+pub trait Pack: Copy {
+    fn pack(self) -> u32;
+    fn unpack(bits: u32) -> Self;
+}
+
+/// A fixed-size table of search results indexed by the Zobrist hash of the positions, that can be
+/// shared between threads without locks.
+// When two positions collide on the same slot, the one searched the deepest is kept.
+// Each slot holds the data of an entry packed in a word, and its hash xored with the data in
+// another. An entry torn by concurrent writes has a hash that does not match, and is ignored.
+pub struct TranspositionTable<T> {
+    slots: Vec<[AtomicU64; 2]>,
+    // Evaluations are only stored packed, so the table is shared whatever their type.
+    evals: PhantomData<fn() -> T>,
+}
+
+// The layout of the data word: the evaluation in the low 32 bits, then the depth, the bound, the
+// best move if any, and a bit set for every stored entry, so that empty slots are never valid.
+const DEPTH_SHIFT: u32 = 32;
+const BOUND_SHIFT: u32 = 40;
+const MOVE_SHIFT: u32 = 42;
+const VALID: u64 = 1 << 63;
+
+impl<T: Pack> TranspositionTable<T> {
+    /// Create a table with room for `size` entries.
+    /// A table of size 0 never stores anything.
+    pub fn new(size: usize) -> Self {
+        Self { slots: (0..size).map(|_| [AtomicU64::new(0), AtomicU64::new(0)]).collect(), evals: PhantomData }
+    }
+
+    /// Return the entry stored for the position with the given hash, if there is one.
+    pub fn probe(&self, hash: u64) -> Option<Entry<T>> {
+        let [key, data] = &self.slots[self.slot(hash)?];
+        let (key, data) = (key.load(Ordering::Relaxed), data.load(Ordering::Relaxed));
+        if data & VALID == 0 || key ^ data != hash {
+            return None;
+        }
+        Some(unpack(hash, data))
+    }
+
+    /// Store an entry, unless its slot holds a deeper search of the same position.
+    pub fn store(&self, entry: Entry<T>) {
+        let Some(i) = self.slot(entry.hash) else { return; };
+        if self.probe(entry.hash).is_some_and(|e| e.depth > entry.depth) {
+            return;
+        }
+        let data = pack(&entry);
+        let [key, slot] = &self.slots[i];
+        key.store(entry.hash ^ data, Ordering::Relaxed);
+        slot.store(data, Ordering::Relaxed);
+    }
+
+    fn slot(&self, hash: u64) -> Option<usize> {
+        if self.slots.is_empty() {
+            None
+        } else {
+            Some((hash % self.slots.len() as u64) as usize)
+        }
+    }
+}
+
+impl Pack for i16 {
+    fn pack(self) -> u32 {
+        self as u16 as u32
+    }
+
+    fn unpack(bits: u32) -> Self {
+        bits as u16 as i16
+    }
+}
+
+impl Pack for i32 {
+    fn pack(self) -> u32 {
+        self as u32
+    }
+
+    fn unpack(bits: u32) -> Self {
+        bits as i32
+    }
+}
+
+fn pack<T: Pack>(entry: &Entry<T>) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 0,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+    // Moves take 6 bits per coordinate and a bit for their presence.
+    let m = entry.best_move.map_or(0, |m| 1 | (m.x() as u64) << 1 | (m.y() as u64) << 7);
+    VALID | entry.eval.pack() as u64 | (entry.depth as u64) << DEPTH_SHIFT | bound << BOUND_SHIFT | m << MOVE_SHIFT
+}
+
+fn unpack<T: Pack>(hash: u64, data: u64) -> Entry<T> {
+    let bound = match (data >> BOUND_SHIFT) & 3 {
+        0 => Bound::Exact,
+        1 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+    let m = data >> MOVE_SHIFT;
+    Entry {
+        hash,
+        depth: (data >> DEPTH_SHIFT) as u8,
+        bound,
+        eval: T::unpack(data as u32),
+        best_move: (m & 1 != 0).then(|| Move::new((m >> 1 & 0x3f) as u8, (m >> 7 & 0x3f) as u8)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(hash: u64, depth: u8, eval: i16) -> Entry<i16> {
+        Entry { hash, depth, bound: Bound::Exact, eval, best_move: None }
+    }
+
+    #[test]
+    fn probe_and_store() {
+        let tt = TranspositionTable::new(16);
+        assert!(tt.probe(3).is_none());
+
+        tt.store(entry(3, 2, 10));
+        assert_eq!(tt.probe(3).unwrap().eval, 10);
+        assert!(tt.probe(19).is_none());
+
+        // A shallower search of the same position does not replace a deeper one.
+        tt.store(entry(3, 1, 20));
+        assert_eq!(tt.probe(3).unwrap().eval, 10);
+        tt.store(entry(3, 4, 30));
+        assert_eq!(tt.probe(3).unwrap().eval, 30);
+
+        // Another position always replaces the entry of its slot.
+        tt.store(entry(19, 0, 40));
+        assert!(tt.probe(3).is_none());
+        assert_eq!(tt.probe(19).unwrap().eval, 40);
+    }
+
+    #[test]
+    fn empty_table() {
+        let tt = TranspositionTable::new(0);
+        tt.store(entry(3, 2, 10));
+        assert!(tt.probe(3).is_none());
+
+        // Empty slots do not match the hash 0.
+        let tt = TranspositionTable::<i16>::new(4);
+        assert!(tt.probe(0).is_none());
+    }
+
+    #[test]
+    fn packing() {
+        let tt = TranspositionTable::new(8);
+        let stored = Entry { hash: 0xdead_beef, depth: 17, bound: Bound::Lower, eval: i32::MIN, best_move: Some(Move::new(9, 3)) };
+        tt.store(stored);
+        let entry = tt.probe(0xdead_beef).unwrap();
+        assert_eq!((entry.depth, entry.bound, entry.eval, entry.best_move), (17, Bound::Lower, i32::MIN, Some(Move::new(9, 3))));
+
+        tt.store(Entry { hash: 5, depth: 0, bound: Bound::Upper, eval: -3, best_move: None });
+        let entry = tt.probe(5).unwrap();
+        assert_eq!((entry.bound, entry.eval, entry.best_move), (Bound::Upper, -3, None));
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        let tt = TranspositionTable::new(64);
+        std::thread::scope(|scope| {
+            for t in 0..4u64 {
+                let tt = &tt;
+                scope.spawn(move || {
+                    for i in 0..1000u64 {
+                        let hash = i * 4 + t;
+                        tt.store(Entry { hash, depth: 1, bound: Bound::Exact, eval: hash as i32, best_move: None });
+                    }
+                });
+            }
+        });
+        // Whatever entries survived are consistent.
+        for hash in 0..4000 {
+            if let Some(entry) = tt.probe(hash) {
+                assert_eq!(entry.eval, hash as i32);
+            }
+        }
+    }
+}
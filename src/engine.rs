@@ -0,0 +1,151 @@
+use crate::{
+    board::Move,
+    game::Game,
+};
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{Receiver, RecvTimeoutError, channel},
+    thread,
+    time::Duration,
+};
+
+// How long an engine may take past its time to answer, to account for the communication.
+const GRACE: Duration = Duration::from_millis(200);
+
+/// An external program playing moves, talking over its standard input and output.
+// For each move, the engine is sent the current board in the format of `String::from(&Board)`,
+// then the line `moves <sequence>` with the plies played since the start of the game as written
+// by `Game::sequence`, then the line `go <milliseconds>` with the time it has to answer.
+// The engine answers with a line holding its move, like `F5`. It is only asked to move when it
+// has a valid move.
+// An engine that does not answer in time is restarted before the next request, as its late answer
+// could otherwise be taken for the answer to that request.
+pub struct Engine {
+    command: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    timed_out: bool,
+}
+
+/// The ways an engine can lose a game by forfeit.
+#[derive(Debug, PartialEq)]
+pub enum EngineError {
+    /// The engine exited or closed its output.
+    Crash,
+    /// The engine did not answer in time.
+    Timeout,
+    /// The engine answered something that is not a valid move.
+    IllegalMove(String),
+}
+
+impl Engine {
+    /// Start the engine with the given command line, arguments being separated by whitespace.
+    pub fn spawn(command: &str) -> std::io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or(std::io::ErrorKind::InvalidInput)?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        // Lines are read on another thread, so that waiting for them can time out.
+        let (sender, lines) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break; };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self { command: command.to_string(), child, stdin, lines, timed_out: false })
+    }
+
+    /// Return the command line of the engine.
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Ask the engine for a move on the current position of the game, within `timeout`.
+    pub fn choose_move(&mut self, game: &Game, timeout: Duration) -> Result<Move, EngineError> {
+        if self.timed_out {
+            *self = Self::spawn(&self.command).map_err(|_| EngineError::Crash)?;
+        }
+
+        let request = format!("{}moves {}\ngo {}\n", String::from(game.board()), game.sequence(), timeout.as_millis());
+        self.stdin.write_all(request.as_bytes())
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| EngineError::Crash)?;
+
+        let answer = match self.lines.recv_timeout(timeout + GRACE) {
+            Ok(answer) => answer,
+            Err(RecvTimeoutError::Timeout) => {
+                self.timed_out = true;
+                return Err(EngineError::Timeout);
+            },
+            Err(RecvTimeoutError::Disconnected) => return Err(EngineError::Crash),
+        };
+        match answer.trim().parse() {
+            Ok(m) if game.board().is_valid_move(&m) => Ok(m),
+            _ => Err(EngineError::IllegalMove(answer)),
+        }
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+
+    fn stub(moves: &str) -> Engine {
+        Engine::spawn(&format!("sh tests/engine/stub.sh {}", moves)).unwrap()
+    }
+
+    #[test]
+    fn moves() {
+        let mut game = Game::new(Board::new(8));
+        let mut engine = stub("d3 C5");
+        let m = engine.choose_move(&game, Duration::from_secs(1)).unwrap();
+        assert_eq!(m, "D3".parse().unwrap());
+        game.play(&m);
+        let m = engine.choose_move(&game, Duration::from_secs(1)).unwrap();
+        assert_eq!(m, "C5".parse().unwrap());
+    }
+
+    #[test]
+    fn forfeits() {
+        let game = Game::new(Board::new(8));
+        let timeout = Duration::from_millis(100);
+        assert_eq!(stub("A1").choose_move(&game, timeout), Err(EngineError::IllegalMove("A1".into())));
+        assert_eq!(stub("resign").choose_move(&game, timeout), Err(EngineError::IllegalMove("resign".into())));
+        assert_eq!(Engine::spawn("true").unwrap().choose_move(&game, timeout), Err(EngineError::Crash));
+        assert_eq!(Engine::spawn("sleep 5").unwrap().choose_move(&game, timeout), Err(EngineError::Timeout));
+        assert!(Engine::spawn("").is_err());
+        assert!(Engine::spawn("./no-such-engine").is_err());
+    }
+
+    #[test]
+    fn late_answer() {
+        // The late answer to the first request would be illegal after D3: the restarted engine
+        // answers the second request instead.
+        let mut game = Game::new(Board::new(8));
+        let mut engine = Engine::spawn("sh tests/engine/slow.sh").unwrap();
+        assert_eq!(engine.choose_move(&game, Duration::from_millis(100)), Err(EngineError::Timeout));
+        game.play(&"D3".parse().unwrap());
+        assert_eq!(engine.choose_move(&game, Duration::from_secs(2)), Ok("C5".parse().unwrap()));
+    }
+}
@@ -0,0 +1,481 @@
+mod ggf;
+
+use crate::board::{Board, Move, ParsingError};
+
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    str::FromStr,
+    time::Duration,
+};
+
+/// A record of a game: its starting position and the plies played since, which can be taken back
+/// and played again.
+// The plies are `Move`s, or `None` when a player had to pass.
+// `times[i]` is the time spent on `plies[i]`, if known.
+// `boards[i]` is the board after the first `i` plies, so `boards[0]` is the starting position.
+// Only the first `current` plies are currently played, the following ones can be redone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    /// The name of the black player, if known.
+    pub black: Option<String>,
+    /// The name of the white player, if known.
+    pub white: Option<String>,
+    /// The result of the game, if known.
+    pub result: Option<String>,
+    /// The time control of the game, in GGF notation (e.g. `05:00//02:00`), if known.
+    pub time_control: Option<String>,
+    plies: Vec<Option<Move>>,
+    times: Vec<Option<Duration>>,
+    boards: Vec<Board>,
+    current: usize,
+}
+
+impl Game {
+    /// Create a game starting from the given position.
+    pub fn new(start: Board) -> Self {
+        Self {
+            black: None,
+            white: None,
+            result: None,
+            time_control: None,
+            plies: vec!(),
+            times: vec!(),
+            boards: vec!(start),
+            current: 0,
+        }
+    }
+
+    /// Return the starting position.
+    pub fn start(&self) -> &Board {
+        &self.boards[0]
+    }
+
+    /// Return the current position.
+    pub fn board(&self) -> &Board {
+        &self.boards[self.current]
+    }
+
+    /// Return every ply recorded, including those that were undone, `None` standing for a pass.
+    pub fn plies(&self) -> &[Option<Move>] {
+        &self.plies
+    }
+
+    /// Return the time spent on every ply recorded, if known.
+    pub fn times(&self) -> &[Option<Duration>] {
+        &self.times
+    }
+
+    /// Return the number of plies currently played.
+    pub fn ply(&self) -> usize {
+        self.current
+    }
+
+    /// Return the boards from the starting position to the current one.
+    /// As boards always have a player who can move, the board before a pass is the same as after.
+    pub fn boards(&self) -> impl Iterator<Item = &Board> {
+        self.boards[..=self.current].iter()
+    }
+
+    /// Play a move on the current position, forgetting the plies that were undone.
+    /// If the opponent has to pass afterwards, the pass is recorded too.
+    /// Return whether the move was valid.
+    pub fn play(&mut self, m: &Move) -> bool {
+        self.push(m, None)
+    }
+
+    /// Like `play`, also recording the time spent on the move.
+    pub fn play_timed(&mut self, m: &Move, time: Duration) -> bool {
+        self.push(m, Some(time))
+    }
+
+    fn push(&mut self, m: &Move, time: Option<Duration>) -> bool {
+        let Some(board) = self.board().play(m) else {
+            return false;
+        };
+        let pass = board.player.is_some() && board.player == self.board().player;
+
+        self.plies.truncate(self.current);
+        self.times.truncate(self.current);
+        self.boards.truncate(self.current + 1);
+        self.plies.push(Some(*m));
+        self.times.push(time);
+        self.boards.push(board);
+        if pass {
+            self.plies.push(None);
+            self.times.push(None);
+            self.boards.push(self.boards[self.current + 1].clone());
+        }
+        self.current = self.plies.len();
+        true
+    }
+
+    /// Take back the last ply played.
+    /// Return whether there was one.
+    pub fn undo(&mut self) -> bool {
+        self.jump(self.current.wrapping_sub(1))
+    }
+
+    /// Play again the last ply taken back.
+    /// Return whether there was one.
+    pub fn redo(&mut self) -> bool {
+        self.jump(self.current + 1)
+    }
+
+    /// Go to the position after the given number of plies, among those recorded.
+    /// Return whether there were enough plies.
+    pub fn jump(&mut self, ply: usize) -> bool {
+        if ply > self.plies.len() {
+            return false;
+        }
+        self.current = ply;
+        true
+    }
+
+    /// Play a sequence of moves from the current position, as written by `sequence`.
+    /// Moves may be separated by whitespace, `,`, `;` or `-`, and are case-insensitive.
+    /// Passes are written `--` or `pass`, and may be omitted, as they are forced.
+    /// Return `InvalidMove` for a token that is not a move, and `IllegalMove` with the number of the
+    /// ply for a move that cannot be played, leaving the plies before played.
+    pub fn play_sequence(&mut self, s: &str) -> Result<(), ParsingError> {
+        let mut pass = self.plies[..self.current].last() == Some(&None);
+        for ply in parse_sequence(s)? {
+            let Some(m) = ply else {
+                if !pass {
+                    return Err(ParsingError::IllegalMove(self.ply() + 1));
+                }
+                pass = false;
+                continue;
+            };
+
+            if !self.play(&m) {
+                return Err(ParsingError::IllegalMove(self.ply() + 1));
+            }
+            pass = self.plies().last() == Some(&None);
+        }
+        Ok(())
+    }
+
+    /// Return the plies played up to the current position as a compact sequence, like `f5d6--c3`.
+    pub fn sequence(&self) -> String {
+        self.plies[..self.current].iter()
+            .map(|p| p.map_or("--".into(), |m| m.to_string().to_lowercase()))
+            .collect()
+    }
+}
+
+// Split a sequence of moves into its plies, `None` standing for a pass.
+// A move is a letter followed by digits, so that moves need no separators.
+fn parse_sequence(s: &str) -> Result<Vec<Option<Move>>, ParsingError> {
+    let is_separator = |c: char| c.is_whitespace() || ",;".contains(c);
+    let mut plies = vec!();
+    let mut rest = s;
+    loop {
+        rest = rest.trim_start_matches(is_separator);
+        if rest.starts_with("--") {
+            plies.push(None);
+            rest = &rest[2..];
+            continue;
+        }
+        rest = rest.trim_start_matches(|c: char| is_separator(c) || c == '-');
+        if rest.is_empty() {
+            return Ok(plies);
+        }
+
+        if rest.get(..4).is_some_and(|p| p.eq_ignore_ascii_case("pass")) {
+            plies.push(None);
+            rest = &rest[4..];
+            continue;
+        }
+
+        let length = rest.char_indices()
+            .skip(1)
+            .find(|(_, c)| !c.is_ascii_digit())
+            .map_or(rest.len(), |(i, _)| i);
+        let token = &rest[..length];
+        match token.parse() {
+            Ok(m) => plies.push(Some(m)),
+            Err(_) => {
+                let end = rest.find(|c: char| is_separator(c) || c == '-').unwrap_or(rest.len());
+                return Err(ParsingError::InvalidMove(rest[..end].to_string()));
+            },
+        }
+        rest = &rest[length..];
+    }
+}
+
+impl From<Board> for Game {
+    fn from(board: Board) -> Self {
+        Self::new(board)
+    }
+}
+
+impl TryFrom<File> for Game {
+    type Error = ParsingError;
+
+    // Read either a GGF record or a transcript.
+    fn try_from(file: File) -> Result<Self, Self::Error> {
+        let mut content = String::new();
+        BufReader::new(file).read_to_string(&mut content)?;
+        if content.trim_start().starts_with("(;") {
+            Self::from_ggf(&content)
+        } else {
+            content.parse()
+        }
+    }
+}
+
+// A transcript is made of header lines `Field: value`, followed by the starting position in the
+// format of boards. As boards never contain colons, a board alone is also a valid transcript.
+// The plies are given in the `Moves` field, as a sequence of moves read by `play_sequence`.
+impl FromStr for Game {
+    type Err = ParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut black, mut white, mut result) = (None, None, None);
+        let mut moves: Vec<&str> = vec!();
+
+        let mut rest = s;
+        while !rest.is_empty() {
+            let (line, next) = rest.split_once('\n').unwrap_or((rest, ""));
+            let content = line.split('#').next().expect("split returns at least one item").trim();
+            if content.is_empty() {
+                rest = next;
+                continue;
+            }
+            let Some((field, value)) = content.split_once(':') else {
+                break;
+            };
+
+            let value = value.trim();
+            match field.trim() {
+                "Black" => black = Some(value.to_string()),
+                "White" => white = Some(value.to_string()),
+                "Result" => result = Some(value.to_string()),
+                "Moves" => moves.extend(value.split_whitespace()),
+                field => return Err(ParsingError::UnknownField(field.to_string())),
+            }
+            rest = next;
+        }
+
+        let mut game = Game::new(rest.parse()?);
+        game.play_sequence(&moves.join(" "))?;
+        game.black = black;
+        game.white = white;
+        game.result = result;
+        Ok(game)
+    }
+}
+
+impl From<&Game> for String {
+    // Create the transcript of a game, up to the current position, for later writing it to a file.
+    fn from(g: &Game) -> Self {
+        let mut out = String::new();
+        for (field, value) in [("Black", &g.black), ("White", &g.white), ("Result", &g.result)] {
+            if let Some(value) = value {
+                out.push_str(&format!("{}: {}\n", field, value));
+            }
+        }
+
+        let plies: Vec<String> = g.plies[..g.current].iter()
+            .map(|p| p.map_or("--".into(), String::from))
+            .collect();
+        out.push_str(&format!("Moves: {}\n", plies.join(" ")));
+
+        out.push_str(&String::from(g.start()));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Find a move after which the opponent has to pass, on a 4x4 board.
+    fn pass() -> (Board, Move) {
+        let mut boards = vec!(Board::new(4));
+        while let Some(board) = boards.pop() {
+            for m in board.moves() {
+                let next = board.play(&m).unwrap();
+                if next.player.is_some() && next.player == board.player {
+                    return (board, m);
+                }
+                boards.push(next);
+            }
+        }
+        unreachable!("there are passes in 4x4 games")
+    }
+
+    #[test]
+    fn play_undo_redo() {
+        let mut game = Game::new(Board::new(8));
+        assert!(!game.undo());
+        assert!(!game.redo());
+
+        let invalid = Board::new(8).moves()[0];
+        let first = game.board().moves()[0];
+        assert!(game.play(&first));
+        assert!(!game.play(&invalid));
+        let second = game.board().moves()[0];
+        assert!(game.play(&second));
+        assert_eq!(game.plies(), &[Some(first), Some(second)]);
+        assert_eq!(game.ply(), 2);
+        let end = game.board().clone();
+
+        assert!(game.undo());
+        assert!(game.undo());
+        assert!(!game.undo());
+        assert_eq!(game.board(), &Board::new(8));
+        assert_eq!(game.plies().len(), 2);
+
+        assert!(game.redo());
+        assert!(game.redo());
+        assert!(!game.redo());
+        assert_eq!(game.board(), &end);
+
+        // Playing after an undo forgets the plies that were undone.
+        game.undo();
+        let other = game.board().moves()[1];
+        assert!(game.play(&other));
+        assert_eq!(game.plies(), &[Some(first), Some(other)]);
+        assert!(!game.redo());
+    }
+
+    #[test]
+    fn jump_and_boards() {
+        let mut game = Game::new(Board::new(6));
+        for _ in 0..5 {
+            let m = game.board().moves()[0];
+            game.play(&m);
+        }
+        let boards: Vec<Board> = game.boards().cloned().collect();
+        assert_eq!(boards.len(), 6);
+        assert_eq!(&boards[0], game.start());
+
+        assert!(game.jump(2));
+        assert_eq!(game.board(), &boards[2]);
+        assert_eq!(game.boards().count(), 3);
+        assert!(game.jump(5));
+        assert_eq!(game.board(), &boards[5]);
+        assert!(!game.jump(6));
+    }
+
+    #[test]
+    fn passes() {
+        let (board, m) = pass();
+        let player = board.player;
+        let mut game = Game::new(board);
+        game.play(&m);
+        assert_eq!(game.plies(), &[Some(m), None]);
+        assert_eq!(game.board().player, player);
+
+        game.undo();
+        assert_eq!(game.board().player, player);
+        game.undo();
+        assert_eq!(game.ply(), 0);
+    }
+
+    #[test]
+    fn game_over() {
+        let mut game = Game::from(Board::new(4));
+        while let Some(&m) = game.board().moves().first() {
+            game.play(&m);
+        }
+        assert_eq!(game.board().player, None);
+        assert_ne!(game.plies().last(), Some(&None));
+    }
+
+    #[test]
+    fn sequences() {
+        let mut game = Game::new(Board::new(8));
+        game.play_sequence("f5d6C3").unwrap();
+        game.play_sequence(" D3, c4;f4 - c5 ").unwrap();
+        assert_eq!(game.ply(), 7);
+        assert_eq!(game.sequence(), "f5d6c3d3c4f4c5");
+
+        let mut other = Game::new(Board::new(8));
+        other.play_sequence(&game.sequence()).unwrap();
+        assert_eq!(other, game);
+
+        // Rows past 9 need no separator either.
+        assert_eq!(parse_sequence("a10b2"), Ok(vec!(Some(Move::new(0, 9)), Some(Move::new(1, 1)))));
+    }
+
+    #[test]
+    fn sequence_passes() {
+        let (board, m) = pass();
+        let mut game = Game::new(board.clone());
+        game.play(&m);
+        let sequence = game.sequence();
+        assert!(sequence.ends_with("--"));
+
+        for sequence in [sequence.clone(), sequence.replace("--", " pass"), sequence.replace("--", "PASS"), sequence.replace("--", "")] {
+            let mut other = Game::new(board.clone());
+            other.play_sequence(&sequence).unwrap();
+            assert_eq!(other, game);
+        }
+    }
+
+    #[test]
+    fn sequence_errors() {
+        let mut game = Game::new(Board::new(8));
+        assert_eq!(game.play_sequence("f5 d6 xx"), Err(ParsingError::InvalidMove("xx".into())));
+        assert_eq!(game.ply(), 0);
+        assert_eq!(game.play_sequence("f5d6 c3, 7f"), Err(ParsingError::InvalidMove("7f".into())));
+        assert_eq!(game.play_sequence("f5d6c3d3a1"), Err(ParsingError::IllegalMove(5)));
+        assert_eq!(game.ply(), 4);
+        assert_eq!(game.play_sequence("c4 --"), Err(ParsingError::IllegalMove(6)));
+        assert_eq!(game.play_sequence("pas"), Err(ParsingError::InvalidMove("pas".into())));
+    }
+
+    #[test]
+    fn transcript() {
+        let mut game = Game::new(Board::new(6));
+        game.black = Some("human".into());
+        game.result = Some("20-16".into());
+        for _ in 0..10 {
+            let m = game.board().moves()[0];
+            game.play(&m);
+        }
+        let transcript = String::from(&game);
+        assert_eq!(transcript.parse(), Ok(game.clone()));
+
+        // Only the plies up to the current position are written.
+        game.jump(4);
+        let mut parsed: Game = String::from(&game).parse().unwrap();
+        assert_eq!(parsed.board(), game.board());
+        assert_eq!(parsed.plies(), &game.plies()[..4]);
+        assert!(parsed.undo());
+
+        let (board, m) = pass();
+        let mut game = Game::new(board);
+        game.play(&m);
+        let transcript = String::from(&game);
+        assert!(transcript.contains(" --"));
+        assert_eq!(transcript.parse(), Ok(game));
+    }
+
+    #[test]
+    fn transcript_format() {
+        let transcript = "# A comment\nBlack: Alice # another comment\n\nMoves: D3 c5\nMoves: e6\nX\n________\n________\n________\n___OX___\n___XO___\n________\n________\n________\n";
+        let game: Game = transcript.parse().unwrap();
+        assert_eq!(game.black.as_deref(), Some("Alice"));
+        assert_eq!(game.white, None);
+        assert_eq!(game.ply(), 3);
+        assert_eq!(game.start(), &Board::new(8));
+
+        // A board alone is a game without any move.
+        let board = String::from(&Board::new(4));
+        assert_eq!(board.parse(), Ok(Game::new(Board::new(4))));
+    }
+
+    #[test]
+    fn transcript_errors() {
+        let board = String::from(&Board::new(4));
+        assert_eq!(format!("Blank: Bob\n{}", board).parse::<Game>(), Err(ParsingError::UnknownField("Blank".into())));
+        assert_eq!(format!("Moves: B1 Z\n{}", board).parse::<Game>(), Err(ParsingError::InvalidMove("Z".into())));
+        assert_eq!(format!("Moves: B1 D4\n{}", board).parse::<Game>(), Err(ParsingError::IllegalMove(2)));
+        assert_eq!(format!("Moves: B1 --\n{}", board).parse::<Game>(), Err(ParsingError::IllegalMove(2)));
+        assert_eq!("Moves: B1\n".parse::<Game>(), Err(ParsingError::EmptyFile));
+    }
+}
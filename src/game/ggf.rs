@@ -0,0 +1,278 @@
+use super::Game;
+use crate::board::{parse_size, Board, ParsingError, Player};
+
+use std::time::Duration;
+
+// A GGF record is a list of fields `NAME[value]` between `(;` and `;)`, as in
+// `(;GM[Othello]PB[alice]PW[bob]TY[8]BO[8 ... *]B[d3//1.50]W[c5]...;)`.
+// `]` and `\` are escaped in values with a `\`.
+// Moves are written `move/evaluation/time`, the last two being optional, and passes `PA`.
+// Times are in seconds, possibly preceded by minutes and hours, as in `1:02:03.5`.
+// GGF has many fields, like the place or the date of the game: those not supported are ignored.
+impl Game {
+    /// Parse the first GGF record of a string.
+    pub fn from_ggf(s: &str) -> Result<Self, ParsingError> {
+        let fields = fields(s)?;
+        let invalid = |name: &str, value: &str| ParsingError::InvalidField(name.to_string(), value.to_string());
+
+        let mut start = None;
+        for (name, value) in &fields {
+            match name.as_str() {
+                "GM" if !value.eq_ignore_ascii_case("othello") => return Err(invalid(name, value)),
+                "BO" => start = Some(Board::from_ggf(value).map_err(|_| invalid(name, value))?),
+                _ => { },
+            }
+        }
+        let Some(start) = start else {
+            return Err(ParsingError::MissingField("BO".into()));
+        };
+
+        let mut game = Game::new(start);
+        let mut pass = false;
+        for (name, value) in &fields {
+            let player = match name.as_str() {
+                "PB" => { game.black = Some(value.clone()); continue; },
+                "PW" => { game.white = Some(value.clone()); continue; },
+                "RE" => { game.result = Some(value.clone()); continue; },
+                "TI" => { game.time_control = Some(value.clone()); continue; },
+                "TY" => {
+                    let size = value.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+                    let size: String = size.chars().take_while(|&c| c.is_ascii_digit() || c == 'x').collect();
+                    let start = game.start();
+                    if parse_size(&size) != Some((start.width(), start.height())) {
+                        return Err(invalid(name, value));
+                    }
+                    continue;
+                },
+                "B" => Player::Black,
+                "W" => Player::White,
+                _ => continue,
+            };
+
+            let mut parts = value.split('/').map(str::trim);
+            let token = parts.next().expect("split returns at least one item");
+            let time = match parts.nth(1).filter(|t| !t.is_empty()) {
+                Some(time) => Some(parse_time(time).ok_or_else(|| invalid(name, value))?),
+                None => None,
+            };
+
+            // Passes are recorded along with the move before, only their time is missing.
+            if token.eq_ignore_ascii_case("PA") {
+                if !pass || game.board().player != Some(player.other()) {
+                    return Err(ParsingError::IllegalMove(game.ply() + 1));
+                }
+                game.times[game.current - 1] = time;
+                pass = false;
+                continue;
+            }
+
+            if game.board().player != Some(player) {
+                return Err(ParsingError::IllegalMove(game.ply() + 1));
+            }
+            let m = token.parse().map_err(|_| invalid(name, value))?;
+            if !game.push(&m, time) {
+                return Err(ParsingError::IllegalMove(game.ply() + 1));
+            }
+            pass = game.plies().last() == Some(&None);
+        }
+
+        Ok(game)
+    }
+
+    /// Parse every GGF record of a string, like the content of a file of a game collection.
+    pub fn from_ggf_collection(s: &str) -> Vec<Result<Self, ParsingError>> {
+        s.match_indices("(;").map(|(i, _)| Self::from_ggf(&s[i..])).collect()
+    }
+
+    /// Create the GGF record of the game, up to the current position.
+    pub fn to_ggf(&self) -> String {
+        let mut out = String::from("(;GM[Othello]");
+        for (field, value) in [("PB", &self.black), ("PW", &self.white), ("RE", &self.result), ("TI", &self.time_control)] {
+            if let Some(value) = value {
+                out.push_str(&format!("{}[{}]", field, escape(value)));
+            }
+        }
+        out.push_str(&format!("TY[{}]BO[{}]", self.start().ggf_size(), self.start().to_ggf()));
+
+        for (i, ply) in self.plies[..self.current].iter().enumerate() {
+            // The board before a pass is the one after the previous move, with its player to move.
+            let player = self.boards[i].player.expect("there is a player to move before a ply");
+            let (player, token) = match ply {
+                Some(m) => (player, m.to_string().to_lowercase()),
+                None => (player.other(), "PA".into()),
+            };
+            let time = self.times[i].map_or(String::new(), |t| format!("//{:.2}", t.as_secs_f64()));
+            out.push_str(&format!("{}[{}{}]", match player {
+                Player::Black => "B",
+                Player::White => "W",
+            }, token, time));
+        }
+
+        out.push_str(";)");
+        out
+    }
+}
+
+// Split the first GGF record of a string into its fields, in order.
+fn fields(s: &str) -> Result<Vec<(String, String)>, ParsingError> {
+    let Some(start) = s.find("(;") else {
+        return Err(ParsingError::EmptyFile);
+    };
+    let mut chars = s[start + 2..].chars();
+
+    let mut fields = vec!();
+    loop {
+        match chars.find(|c| !c.is_whitespace()) {
+            Some(';') => return match chars.next() {
+                Some(')') => Ok(fields),
+                Some(c) => Err(ParsingError::InvalidCharacter(c)),
+                None => Err(ParsingError::UnterminatedRecord),
+            },
+            Some(c) if c.is_ascii_uppercase() => {
+                let mut name = String::from(c);
+                loop {
+                    match chars.next() {
+                        Some('[') => break,
+                        Some(c) if c.is_ascii_uppercase() => name.push(c),
+                        Some(c) => return Err(ParsingError::InvalidCharacter(c)),
+                        None => return Err(ParsingError::UnterminatedRecord),
+                    }
+                }
+
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some('\\') => value.push(chars.next().ok_or(ParsingError::UnterminatedRecord)?),
+                        Some(c) => value.push(c),
+                        None => return Err(ParsingError::UnterminatedRecord),
+                    }
+                }
+                fields.push((name, value));
+            },
+            Some(c) => return Err(ParsingError::InvalidCharacter(c)),
+            None => return Err(ParsingError::UnterminatedRecord),
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(']', "\\]")
+}
+
+fn parse_time(s: &str) -> Option<Duration> {
+    let mut seconds = 0.0;
+    for part in s.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Move;
+
+    fn play(game: &mut Game, moves: &[&str]) {
+        for m in moves {
+            assert!(game.play(&m.parse().unwrap()));
+        }
+    }
+
+    #[test]
+    fn import() {
+        let record = "(;GM[Othello]PC[NEC]DT[2003.12.15_13:24:03.MET]PB[Saio]PW[Zebra]RB[2198.61]\
+            TI[05:00//02:00]TY[8]RE[+12.00]BO[8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *]\
+            B[d3//1.50]W[c5/-2.00/0:12.25]B[f6];)";
+        let game = Game::from_ggf(record).unwrap();
+        assert_eq!(game.black.as_deref(), Some("Saio"));
+        assert_eq!(game.white.as_deref(), Some("Zebra"));
+        assert_eq!(game.result.as_deref(), Some("+12.00"));
+        assert_eq!(game.time_control.as_deref(), Some("05:00//02:00"));
+        assert_eq!(game.plies(), &[Some(Move::new(3, 2)), Some(Move::new(2, 4)), Some(Move::new(5, 5))]);
+        assert_eq!(game.times(), &[Some(Duration::from_millis(1500)), Some(Duration::from_millis(12250)), None]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut game = Game::new(Board::new(6));
+        game.black = Some("Alice [the first]".into());
+        game.time_control = Some("1:00".into());
+        play(&mut game, &["D5"]);
+        assert!(game.play_timed(&game.board().moves()[0], Duration::from_millis(2250)));
+        let m = game.board().moves()[0];
+        game.play(&m);
+        let record = game.to_ggf();
+        assert!(record.contains("PB[Alice [the first\\]]"));
+        assert!(record.contains("W[") && record.contains("//2.25]"));
+        assert_eq!(Game::from_ggf(&record), Ok(game));
+
+        // Larger and rectangular boards keep their shape.
+        for board in [Board::new(14), Board::rectangular(8, 10)] {
+            let mut game = Game::new(board);
+            for _ in 0..2 {
+                let m = game.board().moves()[0];
+                game.play(&m);
+            }
+            let record = game.to_ggf();
+            assert!(record.contains(&format!("TY[{}]", game.start().ggf_size())));
+            assert_eq!(Game::from_ggf(&record), Ok(game));
+        }
+    }
+
+    #[test]
+    fn passes() {
+        // Find a game on a 4x4 board where a player has to pass.
+        let mut games = vec!(Game::new(Board::new(4)));
+        let game = loop {
+            let game = games.pop().expect("there are passes in 4x4 games");
+            if game.plies().contains(&None) {
+                break game;
+            }
+            for m in game.board().moves() {
+                let mut next = game.clone();
+                next.play(&m);
+                games.push(next);
+            }
+        };
+        let record = game.to_ggf();
+        assert!(record.contains("[PA]"));
+        assert_eq!(Game::from_ggf(&record), Ok(game.clone()));
+
+        // Passes may be omitted, as they are forced.
+        let omitted = record.replace("B[PA]", "").replace("W[PA]", "");
+        assert_eq!(Game::from_ggf(&omitted), Ok(game));
+    }
+
+    #[test]
+    fn collection() {
+        let mut game = Game::new(Board::new(8));
+        play(&mut game, &["D3", "C5"]);
+        let collection = format!("{}\n{}\n(;GM[Go]BO[8 *];)\n", game.to_ggf(), Game::new(Board::new(8)).to_ggf());
+        let games = Game::from_ggf_collection(&collection);
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0], Ok(game));
+        assert_eq!(games[1], Ok(Game::new(Board::new(8))));
+        assert_eq!(games[2], Err(ParsingError::InvalidField("GM".into(), "Go".into())));
+    }
+
+    #[test]
+    fn errors() {
+        let bo = "BO[4 ---- -O*- -*O- ---- *]";
+        let parse = |fields: &str| Game::from_ggf(&format!("(;GM[Othello]{};)", fields));
+        assert_eq!(Game::from_ggf("GM[Othello]"), Err(ParsingError::EmptyFile));
+        assert_eq!(parse("PB[Alice]"), Err(ParsingError::MissingField("BO".into())));
+        assert_eq!(parse("BO[4 ---- -O*- -*O- ----]"), Err(ParsingError::InvalidField("BO".into(), "4 ---- -O*- -*O- ----".into())));
+        assert_eq!(parse(&format!("TY[8]{}", bo)), Err(ParsingError::InvalidField("TY".into(), "8".into())));
+        assert_eq!(parse(&format!("TY[4x6]{}", bo)), Err(ParsingError::InvalidField("TY".into(), "4x6".into())));
+        assert_eq!(parse(&format!("{}B[9z]", bo)), Err(ParsingError::InvalidField("B".into(), "9z".into())));
+        assert_eq!(parse(&format!("{}B[z9]", bo)), Err(ParsingError::IllegalMove(1)));
+        assert_eq!(parse(&format!("{}B[b1//soon]", bo)), Err(ParsingError::InvalidField("B".into(), "b1//soon".into())));
+        assert_eq!(parse(&format!("{}W[b1]", bo)), Err(ParsingError::IllegalMove(1)));
+        assert_eq!(parse(&format!("{}B[b1]W[d4]", bo)), Err(ParsingError::IllegalMove(2)));
+        assert_eq!(parse(&format!("{}B[b1]W[PA]", bo)), Err(ParsingError::IllegalMove(2)));
+        assert_eq!(Game::from_ggf(&format!("(;GM[Othello]{}", bo)), Err(ParsingError::UnterminatedRecord));
+        assert_eq!(Game::from_ggf("(;GM[Othello]BO[4 ---- -O*- -*O- ---- *;)"), Err(ParsingError::UnterminatedRecord));
+        assert_eq!(Game::from_ggf("(;GM[Othello] bo[4];)"), Err(ParsingError::InvalidCharacter('b')));
+    }
+}
@@ -1,41 +1,93 @@
 pub mod board;
-use board::{Board, Move, ParsingError, Player};
-mod computer;
+use board::{Board, Move, ParsingError, Player, parse_size};
+pub mod computer;
+use computer::{Mcts, MctsOptions, Patterns, SearchOptions, SearchReport};
+pub mod game;
+use game::Game;
+pub mod nboard;
+pub mod engine;
+use engine::Engine;
+pub mod selfplay;
+pub mod book;
+use book::Book;
+pub mod perft;
+mod tournament;
+mod bench;
+pub mod wthor;
 
 use std::{
+    collections::BTreeMap,
     fs::{File, write},
-    io::{Write, stdin, stdout},
+    io::{BufReader, Write, stdin, stdout},
     path::PathBuf,
-    time::Duration,
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use clap::{ArgAction, arg, command, value_parser};
+use clap::{ArgAction, Command, arg, command, value_parser};
 use rand::{rng, seq::IndexedRandom};
 use heck::ToTitleCase;
 
-#[derive(Clone)]
 enum Tactic {
     Human,
     Random,
-    Computer,
+    Computer(Computer),
+    // A Monte Carlo tree search, keeping its tree from a move to the next.
+    Mcts(Box<Mcts>),
+    External(Engine),
 }
 
 impl Tactic {
-    fn choose_move(&self, board: &Board, timeout: Duration) -> Option<Move> {
+    // With `verbose`, the computer prints the result of its search.
+    fn choose_move(&mut self, game: &mut Game, timeout: Duration, verbose: bool) -> Option<Move> {
         match self {
-            Self::Human => Self::human(board),
-            Self::Random => Self::random(board),
-            Self::Computer => Self::computer(board, timeout),
+            Self::Human => Self::human(game),
+            Self::Random => Self::random(game.board()),
+            Self::Computer(computer) => {
+                if let Some(m) = computer.book_move(game.board()) {
+                    if verbose {
+                        println!("Book move {}.", m);
+                    }
+                    return Some(m);
+                }
+                let report = computer.search(game.board(), timeout);
+                if verbose {
+                    println!("{}", report);
+                }
+                report.best_move
+            },
+            Self::Mcts(mcts) => {
+                let report = mcts.search(game.board(), Some(timeout));
+                if verbose {
+                    println!("{}", report);
+                }
+                report.best_move
+            },
+            Self::External(engine) => Self::external(engine, game, timeout, verbose),
         }
     }
 
-    fn human(board: &Board) -> Option<Move> {
+    fn human(game: &mut Game) -> Option<Move> {
+        let player = game.board().player.expect("the game is not over");
         loop {
+            let board = game.board();
             board.pretty_print();
-            print!("Give your move (e.g. 'A5' or 'a5'), 'q' or 'Q' to quit: ");
+            print!("Give your move (e.g. 'A5' or 'a5'), 'u' to undo, 'r' to redo, 'q' or 'Q' to quit: ");
             stdout().flush().unwrap();
             let mut input = String::new();
             stdin().read_line(&mut input).unwrap();
             let content = input.trim();
+            if content == "u" || content == "undo" {
+                if !Self::rewind(game, player, Game::undo) {
+                    println!("Nothing to undo.");
+                }
+                continue;
+            }
+            if content == "r" || content == "redo" {
+                if !Self::rewind(game, player, Game::redo) {
+                    println!("Nothing to redo.");
+                }
+                continue;
+            }
             if content == "q" || content == "Q" {
                 print!("Quitting, do you want to save this game (y/N)? ");
                 stdout().flush().unwrap();
@@ -44,7 +96,7 @@ impl Tactic {
                 input.make_ascii_uppercase();
                 let content = input.trim();
                 if content == "Y" {
-                    save(board);
+                    save(game);
                 }
                 return None;
             }
@@ -55,46 +107,103 @@ impl Tactic {
         }
     }
 
+    // Undo or redo plies until it is `player`'s turn again.
+    // If there is no such position, stay on the current one and return false.
+    fn rewind(game: &mut Game, player: Player, step: fn(&mut Game) -> bool) -> bool {
+        let ply = game.ply();
+        while step(game) {
+            if game.board().player == Some(player) {
+                return true;
+            }
+        }
+        game.jump(ply);
+        false
+    }
+
     fn random(board: &Board) -> Option<Move> {
         let moves = board.moves();
         moves.choose(&mut rng()).copied()
     }
 
-    fn computer(board: &Board, timeout: Duration) -> Option<Move> {
-        computer::ab_minmax(board, timeout)
+    // An engine that crashes, runs out of time or plays an illegal move forfeits the game.
+    fn external(engine: &mut Engine, game: &Game, timeout: Duration, verbose: bool) -> Option<Move> {
+        match engine.choose_move(game, timeout) {
+            Ok(m) => Some(m),
+            Err(e) => {
+                if verbose {
+                    println!("'{}' forfeits: {:?}.", engine.command(), e);
+                }
+                None
+            },
+        }
+    }
+}
+
+// The computer plays from its opening books while it can, then searches with the given options
+// until its timeout, evaluating positions with the patterns if any, and by counting discs otherwise.
+#[derive(Clone, Default)]
+struct Computer {
+    options: SearchOptions,
+    patterns: Option<Arc<Patterns>>,
+    // The books for the sizes of boards the computer may play on, at most one per size.
+    books: Vec<Arc<Book>>,
+    // How many discs below the best book move the moves played from the books may be.
+    book_margin: i16,
+}
+
+impl Computer {
+    fn book_move(&self, board: &Board) -> Option<Move> {
+        let book = self.books.iter().find(|b| (b.size(), b.size()) == (board.width(), board.height()))?;
+        book.probe(board, self.book_margin, &mut rng())
+    }
+
+    fn search(&self, board: &Board, timeout: Duration) -> SearchReport {
+        let options = SearchOptions { timeout: Some(timeout), ..self.options.clone() };
+        match &self.patterns {
+            None => computer::search(board, &options),
+            Some(patterns) => computer::search_with(board, &options, patterns.as_ref()),
+        }
     }
 }
 
 impl From<&Tactic> for String {
     fn from(t: &Tactic) -> String {
-        match *t {
+        match t {
             Tactic::Human => "human",
             Tactic::Random => "random",
-            Tactic::Computer => "ai",
+            Tactic::Computer(Computer { patterns: None, .. }) => "ai",
+            Tactic::Computer(Computer { patterns: Some(_), .. }) => "ai (patterns)",
+            Tactic::Mcts(_) => "mcts",
+            Tactic::External(engine) => engine.command(),
         }.into()
     }
 }
 
-fn game(mut board: Board, black: &Tactic, white: &Tactic, timeout: Duration) {
+fn game(mut game: Game, black: &mut Tactic, white: &mut Tactic, timeout: Duration, verbose: bool) {
+    game.black = Some(String::from(&*black));
+    game.white = Some(String::from(&*white));
+
     println!("Welcome to this reversi game!");
     println!("{} player ({}) is {} and {} player ({}) is {}.",
         String::from(Player::Black).to_title_case(),
-        char::from(Player::Black), String::from(black),
+        char::from(Player::Black), game.black.as_deref().expect("just set"),
         String::from(Player::White),
-        char::from(Player::White), String::from(white)
+        char::from(Player::White), game.white.as_deref().expect("just set")
         );
 
-    while let Some(player) = board.player {
+    while let Some(player) = game.board().player {
+        let start = Instant::now();
         let chosen_move = match player {
-            Player::Black => black,
-            Player::White => white,
-        }.choose_move(&board, timeout);
+            Player::Black => &mut *black,
+            Player::White => &mut *white,
+        }.choose_move(&mut game, timeout, verbose);
 
         let Some(m) = chosen_move else { break; };
 
-        board = board.play(&m).expect("choose_move should return a valid move");
+        assert!(game.play_timed(&m, start.elapsed()), "choose_move should return a valid move");
     }
 
+    let board = game.board();
     match board.player {
         Some(player) => {
             println!("{} resigned.", String::from(player).to_title_case());
@@ -120,17 +229,109 @@ fn game(mut board: Board, black: &Tactic, white: &Tactic, timeout: Duration) {
     println!("Thanks for playing, see you soon!");
 }
 
-fn save(board: &Board) {
-    print!("Give a filename to save the game (default: 'board.txt'): ");
+fn save(game: &Game) {
+    print!("Give a filename to save the game, ending with '.ggf' for GGF (default: 'game.txt'): ");
     stdout().flush().unwrap();
     let mut input = String::new();
     stdin().read_line(&mut input).unwrap();
     let name = input.trim();
     let name = if name.is_empty() {
-        "board.txt"
+        "game.txt"
     } else { name };
 
-    write(name, String::from(board)).expect("could not write file");
+    let content = if name.ends_with(".ggf") {
+        game.to_ggf() + "\n"
+    } else {
+        String::from(game)
+    };
+    write(name, content).expect("could not write file");
+}
+
+// Print a summary of every game of a WTHOR database, then statistics over all of them.
+fn wthor(file: &PathBuf) -> Result<(), ParsingError> {
+    let reader = wthor::Reader::new(BufReader::new(File::open(file)?))?;
+    let header = reader.header().clone();
+    let (year, month, day) = header.created;
+    println!("{} games played in {}, file created on {}-{:02}-{:02}.", header.games, header.year, year, month, day);
+
+    let mut illegal = 0;
+    // Black wins, draws and white wins, according to the recorded scores.
+    let mut results = [0; 3];
+    let mut black_discs = 0;
+    let mut first_moves: BTreeMap<String, u32> = BTreeMap::new();
+    let mut count = 0;
+    for (i, record) in reader.enumerate() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                println!("Game {}: unreadable record ({:?}), stopping.", i + 1, e);
+                break;
+            },
+        };
+        count += 1;
+
+        let summary = format!("Game {}: tournament {}, black {} vs white {}, {} moves, score {}-{} (theoretical {}-{})",
+            i + 1, record.tournament, record.black, record.white, record.moves.len(),
+            record.score, 64 - record.score, record.theoretical_score, 64 - record.theoretical_score);
+        match record.replay() {
+            Ok(game) => {
+                let (black, white) = game.board().score();
+                println!("{}, final board {}-{}.", summary, black, white);
+                if let Some(Some(m)) = game.plies().first() {
+                    *first_moves.entry(m.to_string()).or_default() += 1;
+                }
+            },
+            Err(ParsingError::IllegalMove(ply)) => {
+                println!("{}, illegal move at ply {}.", summary, ply);
+                illegal += 1;
+            },
+            Err(e) => {
+                println!("{}, {:?}.", summary, e);
+                illegal += 1;
+            },
+        }
+
+        results[match record.score.cmp(&32) {
+            std::cmp::Ordering::Greater => 0,
+            std::cmp::Ordering::Equal => 1,
+            std::cmp::Ordering::Less => 2,
+        }] += 1;
+        black_discs += record.score as u32;
+    }
+
+    println!("{} games read, {} with illegal moves.", count, illegal);
+    if count > 0 {
+        println!("Black wins: {}, draws: {}, white wins: {}.", results[0], results[1], results[2]);
+        println!("Average score: {:.2}-{:.2}.", black_discs as f64 / count as f64, 64.0 - black_discs as f64 / count as f64);
+        let first_moves: Vec<String> = first_moves.iter().map(|(m, n)| format!("{} ({})", m, n)).collect();
+        println!("First moves: {}.", first_moves.join(", "));
+    }
+    Ok(())
+}
+
+// Parse the shape of a board given as `<COLUMNS>x<ROWS>`.
+fn parse_shape(s: &str) -> Result<(u8, u8), String> {
+    match s.split_once('x').and_then(|_| parse_size(s)) {
+        Some(shape) => Ok(shape),
+        None => Err(format!("expected numbers of columns and rows between 2 and {}, e.g. '8x10'", board::MAX_SIZE)),
+    }
+}
+
+// Return the board given by the `size` and `shape` arguments, the shape taking precedence.
+fn start_board(matches: &clap::ArgMatches) -> Board {
+    match matches.get_one::<(u8, u8)>("shape") {
+        Some(&(width, height)) => Board::rectangular(width, height),
+        None => Board::new(matches.get_one::<u8>("size").expect("default ensures there is always a value") * 2),
+    }
+}
+
+// Check that the board has a shape the command can handle, so that it fails at once with the shape
+// rather than later, or by playing poorly.
+fn check_shape(board: &Board, supported: impl Fn(u8, u8) -> bool) -> Result<(), ParsingError> {
+    match supported(board.width(), board.height()) {
+        true => Ok(()),
+        false => Err(ParsingError::InvalidField("shape".into(), format!("{}x{}", board.width(), board.height()))),
+    }
 }
 
 pub fn run() -> Result<(), ParsingError> {
@@ -139,8 +340,10 @@ pub fn run() -> Result<(), ParsingError> {
             .action(ArgAction::SetTrue)
         ).arg(arg!(-s --size <SIZE> "board size")
             .value_parser(value_parser!(u8)
-                .range(1..6)
+                .range(1..8)
             ).default_value("4")
+        ).arg(arg!(--shape <SHAPE> "board of <COLUMNS>x<ROWS> squares, overriding the size (e.g. '8x10')")
+            .value_parser(parse_shape)
         ).arg(arg!(-t --timeout <TIMEOUT> "set AI timeout")
             .value_parser(value_parser!(u64)
                 .range(1..)
@@ -148,46 +351,401 @@ pub fn run() -> Result<(), ParsingError> {
         ).arg(arg!(-b [BLACK] "set tactic of black player")
             .long("black-ai")
             .value_parser(value_parser!(u8)
-                .range(0..3)
+                .range(0..4)
             ).default_value("0")
         ).arg(arg!(-w [WHITE] "set tactic of white player")
             .long("white-ai")
             .value_parser(value_parser!(u8)
-                .range(0..3)
+                .range(0..4)
             ).default_value("0")
+        ).arg(arg!(--"black-engine" <COMMAND> "let an external engine play black, overriding its tactic")
+        ).arg(arg!(--"white-engine" <COMMAND> "let an external engine play white, overriding its tactic")
         ).arg(arg!(-c --contest "enable 'contest' mode")
             .action(ArgAction::SetTrue)
+        ).arg(arg!(--json "in contest mode, print the result of the search as JSON")
+            .action(ArgAction::SetTrue)
+        ).arg(arg!(-p --protocol <PROTOCOL> "speak an engine protocol on stdin and stdout")
+            .value_parser(["nboard"])
+        ).arg(arg!(-m --moves <SEQ> "play a sequence of moves (e.g. 'f5d6c3') before starting")
+        ).arg(arg!(--weights <FILE> "let the computer evaluate positions with pattern weights written by 'train'")
+            .value_parser(value_parser!(PathBuf))
+        ).arg(arg!(-j --threads <THREADS> "number of threads of the computer's search")
+            .value_parser(value_parser!(u32)
+                .range(1..)
+            ).default_value("1")
+        ).arg(arg!(--"opening-book" <FILE> "let the computer play from an opening book written by 'book', once per board size")
+            .value_parser(value_parser!(PathBuf))
+            .action(ArgAction::Append)
+        ).arg(arg!(--"book-margin" <DISCS> "let the computer play book moves up to this many discs below the best one")
+            .value_parser(value_parser!(i16)
+                .range(0..)
+            ).default_value("1")
         ).arg(arg!([FILE])
             .value_parser(value_parser!(PathBuf))
-        ).get_matches();
+        ).subcommand(Command::new("wthor")
+            .about("print the games of a WTHOR database")
+            .arg(arg!(<FILE> "WTHOR database (.wtb)")
+                .value_parser(value_parser!(PathBuf))
+            )
+        ).subcommand(Command::new("tournament")
+            .about("play a round-robin tournament between tactics")
+            .arg(arg!(<PARTICIPANTS> ... "'random', 'ai', 'ai:<depth>', 'patterns:<file>', 'mcts[:<exploration>[:random|light]]' or 'engine:<command>'")
+                .value_parser(value_parser!(tournament::Participant))
+                .num_args(2..)
+            ).arg(arg!(-s --size <SIZE> "board size")
+                .value_parser(value_parser!(u8)
+                    .range(1..8)
+                ).default_value("4")
+            ).arg(arg!(-t --time <MILLISECONDS> "time per move")
+                .value_parser(value_parser!(u64)
+                    .range(1..)
+                ).default_value("100")
+            ).arg(arg!(-n --openings <N> "number of random openings, each played with both colors by each pair")
+                .value_parser(value_parser!(u32)
+                    .range(1..)
+                ).default_value("10")
+            ).arg(arg!(--"opening-plies" <PLIES> "number of random plies of the openings")
+                .value_parser(value_parser!(usize))
+                .default_value("4")
+            ).arg(arg!(--book <FILE> "play the openings of a file, one sequence of moves per line")
+                .value_parser(value_parser!(PathBuf))
+            ).arg(arg!(-j --threads <THREADS> "number of games played in parallel")
+                .value_parser(value_parser!(u32)
+                    .range(1..)
+                )
+            ).arg(arg!(--seed <SEED> "seed of the random openings")
+                .value_parser(value_parser!(u64))
+            ).arg(arg!(--sprt <ELO> "stop once the Elo difference of the first two participants is shown to be below the first bound or above the second")
+                .value_parser(value_parser!(f64))
+                .num_args(2)
+                .allow_negative_numbers(true)
+            ).arg(arg!(--csv <FILE> "export the games as CSV")
+                .value_parser(value_parser!(PathBuf))
+            ).arg(arg!(--json <FILE> "export the games and results as JSON")
+                .value_parser(value_parser!(PathBuf))
+            )
+        ).subcommand(Command::new("selfplay")
+            .about("play the computer against itself and write the positions reached, for training")
+            .arg(arg!(<OUTPUT> "file the positions are written to")
+                .value_parser(value_parser!(PathBuf))
+            ).arg(arg!(-s --size <SIZE> "board size")
+                .value_parser(value_parser!(u8)
+                    .range(1..6)
+                ).default_value("4")
+            ).arg(arg!(-n --games <N> "number of games")
+                .value_parser(value_parser!(u32)
+                    .range(1..)
+                ).default_value("100")
+            ).arg(arg!(-d --depth <DEPTH> "depth of the searches")
+                .value_parser(value_parser!(u8)
+                    .range(1..)
+                ).default_value("4")
+            ).arg(arg!(-r --"random-rate" <RATE> "probability of playing a random move instead of the best one")
+                .value_parser(value_parser!(f64))
+                .default_value("0.1")
+            ).arg(arg!(--"opening-plies" <PLIES> "number of random plies before positions are recorded")
+                .value_parser(value_parser!(usize))
+                .default_value("8")
+            ).arg(arg!(--seed <SEED> "seed of the random choices")
+                .value_parser(value_parser!(u64))
+                .default_value("0")
+            ).arg(arg!(-j --threads <THREADS> "number of games played in parallel")
+                .value_parser(value_parser!(u32)
+                    .range(1..)
+                )
+            ).arg(arg!(--text "write one position per line instead of binary records")
+                .action(ArgAction::SetTrue)
+            )
+        ).subcommand(Command::new("bench")
+            .about("measure the speed of move generation, and the speedup of the parallel search on a fixed suite of positions")
+            .arg(arg!(-d --depth <DEPTH> "depth of the searches")
+                .value_parser(value_parser!(u8)
+                    .range(1..)
+                ).default_value("8")
+            ).arg(arg!(-p --perft <DEPTH> "depth of the perft comparing the 8x8 and generic boards")
+                .value_parser(value_parser!(u8))
+                .default_value("8")
+            ).arg(arg!(-j --threads <THREADS> "number of threads compared to a single one")
+                .value_parser(value_parser!(u32)
+                    .range(1..)
+                )
+            )
+        ).subcommand(Command::new("book")
+            .about("build an opening book from games, scoring its moves with searches")
+            .arg(arg!(<OUTPUT> "file of the book, extended if it exists")
+                .value_parser(value_parser!(PathBuf))
+            ).arg(arg!(<GAMES> ... "games to learn from, as transcripts or GGF")
+                .value_parser(value_parser!(PathBuf))
+            ).arg(arg!(-p --plies <PLIES> "number of plies of each game added to the book")
+                .value_parser(value_parser!(usize))
+                .default_value("16")
+            ).arg(arg!(-d --depth <DEPTH> "depth of the searches scoring the moves")
+                .value_parser(value_parser!(u8)
+                    .range(1..)
+                ).default_value("8")
+            ).arg(arg!(-j --threads <THREADS> "number of threads of the searches")
+                .value_parser(value_parser!(u32)
+                    .range(1..)
+                ).default_value("1")
+            )
+        ).subcommand(Command::new("perft")
+            .about("count the leaves of the tree of the games to a depth, to check move generation")
+            .arg(arg!(<DEPTH> "number of plies, passes included")
+                .value_parser(value_parser!(u8))
+            ).arg(arg!(-s --size <SIZE> "board size")
+                .value_parser(value_parser!(u8)
+                    .range(1..8)
+                ).default_value("4")
+            ).arg(arg!(--shape <SHAPE> "board of <COLUMNS>x<ROWS> squares, overriding the size (e.g. '8x10')")
+                .value_parser(parse_shape)
+            ).arg(arg!(-m --moves <SEQ> "play a sequence of moves (e.g. 'f5d6c3') before counting")
+            ).arg(arg!(--split "print the count under each move of the position")
+                .action(ArgAction::SetTrue)
+            )
+        ).subcommand(Command::new("train")
+            .about("fit pattern weights to positions written by 'selfplay'")
+            .arg(arg!(<SAMPLES> ... "files of positions")
+                .value_parser(value_parser!(PathBuf))
+            ).arg(arg!(-o --output <FILE> "file the weights are written to")
+                .value_parser(value_parser!(PathBuf))
+                .required(true)
+            ).arg(arg!(--text "read files with one position per line instead of binary records")
+                .action(ArgAction::SetTrue)
+            ).arg(arg!(--phases <PHASES> "number of phases of the game with their own weights")
+                .value_parser(value_parser!(u8)
+                    .range(1..)
+                ).default_value("4")
+            ).arg(arg!(-e --epochs <EPOCHS> "number of passes over the positions")
+                .value_parser(value_parser!(usize))
+                .default_value("20")
+            ).arg(arg!(-l --"learning-rate" <RATE> "step of the gradient descent")
+                .value_parser(value_parser!(f32))
+                .default_value("0.1")
+            ).arg(arg!(--validation <FRACTION> "fraction of the positions kept aside to measure the error")
+                .value_parser(value_parser!(f64))
+                .default_value("0.1")
+            ).arg(arg!(--seed <SEED> "seed of the order of the positions")
+                .value_parser(value_parser!(u64))
+                .default_value("0")
+            )
+        ).args_conflicts_with_subcommands(true)
+        .get_matches();
+
+    match matches.subcommand() {
+        Some(("wthor", matches)) => {
+            return wthor(matches.get_one::<PathBuf>("FILE").expect("the file is required"));
+        },
+        Some(("tournament", matches)) => {
+            let sprt: Option<Vec<f64>> = matches.get_many::<f64>("sprt").map(|e| e.copied().collect());
+            let options = tournament::Options {
+                participants: matches.get_many("PARTICIPANTS").expect("participants are required").cloned().collect(),
+                size: matches.get_one::<u8>("size").expect("default ensures there is always a value") * 2,
+                time: Duration::from_millis(*matches.get_one("time").expect("default ensures there is always a value")),
+                openings: *matches.get_one::<u32>("openings").expect("default ensures there is always a value") as usize,
+                opening_plies: *matches.get_one("opening-plies").expect("default ensures there is always a value"),
+                book: matches.get_one("book").cloned(),
+                threads: matches.get_one::<u32>("threads").map(|&n| n as usize)
+                    .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get())),
+                seed: matches.get_one("seed").copied(),
+                sprt: sprt.map(|e| (e[0], e[1])),
+                csv: matches.get_one("csv").cloned(),
+                json: matches.get_one("json").cloned(),
+            };
+            // Weights only evaluate boards of their own size.
+            for participant in &options.participants {
+                if let tournament::Participant::Patterns(_, patterns) = participant {
+                    check_shape(&Board::new(options.size), |width, height| width == patterns.size() && height == patterns.size())?;
+                }
+            }
+            tournament::run(&options)?;
+            return Ok(());
+        },
+        Some(("selfplay", matches)) => {
+            let random_rate = *matches.get_one::<f64>("random-rate").expect("default ensures there is always a value");
+            if !(0.0..=1.0).contains(&random_rate) {
+                return Err(ParsingError::InvalidField("random-rate".into(), random_rate.to_string()));
+            }
+            let options = selfplay::Options {
+                games: *matches.get_one::<u32>("games").expect("default ensures there is always a value") as usize,
+                size: matches.get_one::<u8>("size").expect("default ensures there is always a value") * 2,
+                depth: *matches.get_one("depth").expect("default ensures there is always a value"),
+                random_rate,
+                opening_plies: *matches.get_one("opening-plies").expect("default ensures there is always a value"),
+                seed: *matches.get_one("seed").expect("default ensures there is always a value"),
+                threads: matches.get_one::<u32>("threads").map(|&n| n as usize)
+                    .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get())),
+                output: matches.get_one::<PathBuf>("OUTPUT").expect("the output is required").clone(),
+                text: *matches.get_one::<bool>("text").expect("flag always has value"),
+            };
+            selfplay::run(&options)?;
+            return Ok(());
+        },
+        Some(("bench", matches)) => {
+            let threads = matches.get_one::<u32>("threads").map(|&n| n as usize)
+                .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+            return bench::run(*matches.get_one("depth").expect("default ensures there is always a value"), threads,
+                *matches.get_one("perft").expect("default ensures there is always a value"));
+        },
+        Some(("book", matches)) => {
+            let output = matches.get_one::<PathBuf>("OUTPUT").expect("the output is required");
+            let mut games = vec!();
+            for file in matches.get_many::<PathBuf>("GAMES").expect("games are required") {
+                let game = Game::try_from(File::open(file)?)?;
+                check_shape(game.start(), |width, height| width == height && Book::new(width).is_ok())?;
+                games.push(game);
+            }
+            let mut book = match output.exists() {
+                true => Book::load(output)?,
+                // Games on boards that are not square of this size are rejected by `add_game`.
+                false => Book::new(games[0].start().width())?,
+            };
+            let plies = *matches.get_one("plies").expect("default ensures there is always a value");
+            for game in &games {
+                book.add_game(game, plies)?;
+            }
+            let options = SearchOptions {
+                timeout: None,
+                depth: Some(*matches.get_one("depth").expect("default ensures there is always a value")),
+                threads: *matches.get_one::<u32>("threads").expect("default ensures there is always a value") as usize,
+                ..Default::default()
+            };
+            let searched = book.evaluate(&options);
+            println!("{} games added, {} moves searched, {} positions in the book.", games.len(), searched, book.len());
+            return book.save(output);
+        },
+        Some(("perft", matches)) => {
+            let mut game = Game::new(start_board(matches));
+            if let Some(sequence) = matches.get_one::<String>("moves") {
+                game.play_sequence(sequence)?;
+            }
+            perft::run(game.board(), *matches.get_one("DEPTH").expect("the depth is required"),
+                *matches.get_one::<bool>("split").expect("flag always has value"));
+            return Ok(());
+        },
+        Some(("train", matches)) => {
+            let validation = *matches.get_one::<f64>("validation").expect("default ensures there is always a value");
+            if !(0.0..1.0).contains(&validation) {
+                return Err(ParsingError::InvalidField("validation".into(), validation.to_string()));
+            }
+            let text = *matches.get_one::<bool>("text").expect("flag always has value");
+            let mut samples = vec!();
+            for file in matches.get_many::<PathBuf>("SAMPLES").expect("samples are required") {
+                samples.extend(if text {
+                    selfplay::read_text(&std::fs::read_to_string(file)?)?
+                } else {
+                    selfplay::read(&std::fs::read(file)?)?
+                });
+            }
+            println!("{} positions read.", samples.len());
+            let training = computer::Training {
+                phases: *matches.get_one("phases").expect("default ensures there is always a value"),
+                epochs: *matches.get_one("epochs").expect("default ensures there is always a value"),
+                learning_rate: *matches.get_one("learning-rate").expect("default ensures there is always a value"),
+                validation,
+                seed: *matches.get_one("seed").expect("default ensures there is always a value"),
+            };
+            let examples: Vec<_> = samples.iter().map(selfplay::Sample::example).collect();
+            Patterns::fit(&examples, &training, |epoch, error, validation| match validation {
+                Some(validation) => println!("Epoch {}: error {:.3}, validation error {:.3}.", epoch, error, validation),
+                None => println!("Epoch {}: error {:.3}.", epoch, error),
+            })?
+                .save(matches.get_one::<PathBuf>("output").expect("the output is required"))?;
+            return Ok(());
+        },
+        _ => { },
+    }
 
-    let size = matches.get_one::<u8>("size").expect("default ensures there is always a value") * 2;
     let timeout = Duration::from_secs(*matches.get_one::<u64>("timeout").expect("default ensures there is always a value"));
     let contest = *matches.get_one::<bool>("contest").expect("flag always has value");
-    let _verbose = matches.get_one::<bool>("verbose").expect("flag always has value");
+    let verbose = *matches.get_one::<bool>("verbose").expect("flag always has value");
+    let json = *matches.get_one::<bool>("json").expect("flag always has value");
+    let search_options = SearchOptions {
+        threads: *matches.get_one::<u32>("threads").expect("default ensures there is always a value") as usize,
+        ..Default::default()
+    };
+    let patterns = match matches.get_one::<PathBuf>("weights") {
+        Some(file) => Some(Arc::new(Patterns::load(file)?)),
+        None => None,
+    };
+    let mut books: Vec<Arc<Book>> = vec!();
+    for file in matches.get_many::<PathBuf>("opening-book").into_iter().flatten() {
+        let book = Book::load(file)?;
+        if books.iter().any(|b| b.size() == book.size()) {
+            return Err(ParsingError::InvalidField("opening-book".into(), file.display().to_string()));
+        }
+        books.push(Arc::new(book));
+    }
+    let computer = Computer {
+        options: search_options,
+        patterns,
+        books,
+        book_margin: *matches.get_one("book-margin").expect("default ensures there is always a value"),
+    };
 
-    let black_ai = match matches.get_one::<u8>("BLACK") {
+    if matches.get_one::<String>("protocol").is_some_and(|p| p == "nboard") {
+        if let Some(patterns) = &computer.patterns {
+            check_shape(&Board::new(8), |width, height| width == patterns.size() && height == patterns.size())?;
+        }
+        let options = SearchOptions { timeout: Some(timeout), ..computer.options };
+        let book = computer.books.iter().find(|b| b.size() == 8).map(Arc::as_ref);
+        return Ok(nboard::serve(stdin().lock(), stdout(), &options, computer.patterns.as_deref(), book, computer.book_margin)?);
+    }
+
+    let mut black_ai = match matches.get_one::<u8>("BLACK") {
         Some(0) => Tactic::Human,
         Some(1) => Tactic::Random,
-        Some(2) => Tactic::Computer,
+        Some(2) => Tactic::Computer(computer.clone()),
+        Some(3) => Tactic::Mcts(Box::new(Mcts::new(MctsOptions::default()))),
         _ => unreachable!(),
     };
-    let white_ai = match matches.get_one::<u8>("WHITE") {
+    let mut white_ai = match matches.get_one::<u8>("WHITE") {
         Some(0) => Tactic::Human,
         Some(1) => Tactic::Random,
-        Some(2) => Tactic::Computer,
+        Some(2) => Tactic::Computer(computer.clone()),
+        Some(3) => Tactic::Mcts(Box::new(Mcts::new(MctsOptions::default()))),
         _ => unreachable!(),
     };
+    if let Some(command) = matches.get_one::<String>("black-engine") {
+        black_ai = Tactic::External(Engine::spawn(command)?);
+    }
+    if let Some(command) = matches.get_one::<String>("white-engine") {
+        white_ai = Tactic::External(Engine::spawn(command)?);
+    }
 
-    let board = match matches.get_one::<PathBuf>("FILE") {
-        Some(file) => Board::try_from(File::open(file)?)?,
-        _ => Board::new(size),
+    let mut record = match matches.get_one::<PathBuf>("FILE") {
+        Some(file) => Game::try_from(File::open(file)?)?,
+        _ => Game::new(start_board(&matches)),
     };
+    if let Some(sequence) = matches.get_one::<String>("moves") {
+        record.play_sequence(sequence)?;
+    }
+    if let Some(patterns) = &computer.patterns {
+        check_shape(record.board(), |width, height| width == patterns.size() && height == patterns.size())?;
+    }
 
     if contest {
-        println!("{}", String::from(Tactic::Computer.choose_move(&board, timeout).unwrap()));
+        let book_move = computer.book_move(record.board());
+        let report = match book_move {
+            // A book move is reported as a search of no depth.
+            Some(m) => SearchReport { best_move: Some(m), score: None, pv: vec!(m), depth: 0, nodes: 0, elapsed: Duration::ZERO },
+            None => computer.search(record.board(), timeout),
+        };
+        if json {
+            println!("{}", report.to_json());
+        } else {
+            match book_move {
+                Some(m) if verbose => println!("Book move {}.", m),
+                None if verbose => println!("{}", report),
+                _ => { },
+            }
+            // A finished game is not an error: as in JSON, the output tells there is no move.
+            match report.best_move {
+                Some(m) => println!("{}", m),
+                None => println!("The game is over, there is no move to play."),
+            }
+        }
     } else {
-        game(board, &black_ai, &white_ai, timeout);
+        game(record, &mut black_ai, &mut white_ai, timeout, verbose);
     }
 
     Ok(())
@@ -1,3 +1,6 @@
 fn main () {
-    let _ = rustversi::run();
+    if let Err(e) = rustversi::run() {
+        eprintln!("Error: {:?}", e);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,201 @@
+use crate::{
+    board::{Board, Move, Player},
+    book::Book,
+    computer::{self, DiscCount, Evaluator, Patterns, Score, SearchOptions},
+    game::Game,
+};
+
+use std::{
+    cmp::Reverse,
+    io::{BufRead, Write},
+};
+
+use rand::rng;
+
+/// Speak the NBoard protocol, reading commands from `input` and answering on `output`, until the
+/// `quit` command or the end of the input.
+/// The computer plays from the book while it can, then searches with the given options, to the
+/// depth set by the GUI if any, evaluating positions with the patterns if any, and by counting
+/// discs otherwise.
+// The commands handled are `nboard`, `set game`, `set depth`, `move`, `hint`, `go`, `ping` and
+// `quit`. Others, like `set contempt` or `learn`, are ignored.
+pub fn serve(input: impl BufRead, mut output: impl Write, options: &SearchOptions, patterns: Option<&Patterns>,
+    book: Option<&Book>, book_margin: i16) -> std::io::Result<()> {
+    let mut game = Game::new(Board::new(8));
+    let mut options = options.clone();
+
+    for line in input.lines() {
+        let line = line?;
+        let (command, arguments) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let arguments = arguments.trim();
+        match command {
+            "nboard" => writeln!(output, "set myname {}", env!("CARGO_PKG_NAME"))?,
+            "ping" => writeln!(output, "pong {}", arguments)?,
+            "quit" => break,
+            "set" => {
+                let (variable, value) = arguments.split_once(' ').unwrap_or((arguments, ""));
+                match variable {
+                    "game" => match Game::from_ggf(value) {
+                        Ok(g) => game = g,
+                        Err(e) => writeln!(output, "status invalid game: {:?}", e)?,
+                    },
+                    "depth" => match value.trim().parse() {
+                        Ok(d) if d > 0 => options.depth = Some(d),
+                        _ => writeln!(output, "status invalid depth: {}", value)?,
+                    },
+                    _ => { },
+                }
+            },
+            "move" => {
+                // Moves come with an optional evaluation and time, as in `F5/1.00/2.5`.
+                let token = arguments.split('/').next().expect("split returns at least one item");
+                // Passes are already recorded with the move before.
+                if token.eq_ignore_ascii_case("PA") {
+                    continue;
+                }
+                match token.parse() {
+                    Ok(m) if game.play(&m) => { },
+                    _ => writeln!(output, "status illegal move: {}", token)?,
+                }
+            },
+            "go" => {
+                writeln!(output, "status thinking")?;
+                let best_move = match book.and_then(|b| b.probe(game.board(), book_margin, &mut rng())) {
+                    Some(m) => Some(m),
+                    None => match patterns {
+                        None => computer::search(game.board(), &options).best_move,
+                        Some(patterns) => computer::search_with(game.board(), &options, patterns).best_move,
+                    },
+                };
+                writeln!(output, "=== {}", best_move.map_or("PA".into(), |m| m.to_string()))?;
+                writeln!(output, "status")?;
+            },
+            "hint" => {
+                let Ok(count) = arguments.parse::<usize>() else {
+                    writeln!(output, "status invalid hint: {}", arguments)?;
+                    continue;
+                };
+                writeln!(output, "status thinking")?;
+                let hints = match patterns {
+                    None => hints(game.board(), &options, &DiscCount),
+                    Some(patterns) => hints(game.board(), &options, patterns),
+                };
+                for (m, eval, depth) in hints.into_iter().take(count) {
+                    writeln!(output, "search {} {} 0 {}", m, eval, depth)?;
+                }
+                writeln!(output, "status")?;
+            },
+            _ => { },
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+// Evaluate every move of the position by searching the position after it, to one ply less, and
+// return them best first with their evaluation and depth. The moves share the timeout.
+fn hints<E: Evaluator<Score = i32> + Sync>(board: &Board, options: &SearchOptions, evaluator: &E) -> Vec<(Move, i32, u8)> {
+    let Some(player) = board.player else {
+        return vec!();
+    };
+    let moves = board.moves();
+    let options = SearchOptions {
+        timeout: options.timeout.map(|t| t / moves.len() as u32),
+        depth: options.depth.map(|d| d - 1),
+        ..options.clone()
+    };
+    let squares = board.width() as i32 * board.height() as i32;
+
+    let mut hints: Vec<(Move, i32, u8)> = moves.into_iter().map(|m| {
+        let next = board.play(&m).expect("the moves are valid");
+        let (eval, depth) = match next.player {
+            None => {
+                let (black, white) = next.score();
+                let difference = black as i32 - white as i32;
+                (if player == Player::Black { difference } else { -difference }, 0)
+            },
+            Some(_) if options.depth == Some(0) => (eval(Score::Heuristic(evaluator.evaluate(&next, &player)), squares), 0),
+            Some(next_player) => {
+                let report = computer::search_with(&next, &options, evaluator);
+                let eval = report.score.map_or(0, |s| eval(s, squares));
+                (if next_player == player { eval } else { -eval }, report.depth)
+            },
+        };
+        (m, eval, depth + 1)
+    }).collect();
+    hints.sort_by_key(|&(_, eval, _)| Reverse(eval));
+    hints
+}
+
+// The evaluation of a score for NBoard, where finished games are disc differences. Heuristic
+// scores are given as they are, but for the won and lost games the search finds, which become the
+// largest disc differences.
+fn eval(score: Score<i32>, squares: i32) -> i32 {
+    match score {
+        Score::Exact(score) => score,
+        Score::Heuristic(i32::MAX) => squares,
+        Score::Heuristic(i32::MIN) => -squares,
+        Score::Heuristic(score) => score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    fn serve_str(input: &str) -> String {
+        let mut output = vec!();
+        let options = SearchOptions { timeout: Some(Duration::from_secs(1)), ..Default::default() };
+        serve(input.as_bytes(), &mut output, &options, None, None, 0).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn handshake() {
+        assert_eq!(serve_str("nboard 2\nping 1\nset contempt 0\nping 2\n"), "set myname rustversi\npong 1\npong 2\n");
+        assert_eq!(serve_str("ping 1\nquit\nping 2\n"), "pong 1\n");
+    }
+
+    #[test]
+    fn go() {
+        let ggf = Game::new(Board::new(8)).to_ggf();
+        let output = serve_str(&format!("nboard 2\nset depth 2\nset game {}\nmove F5\nmove d6/0.00/1.2\ngo\nping 3\n", ggf));
+        let lines: Vec<&str> = output.lines().collect();
+        let mut game = Game::new(Board::new(8));
+        game.play_sequence("f5d6").unwrap();
+        let m = lines[2].strip_prefix("=== ").unwrap().parse().unwrap();
+        assert!(game.board().is_valid_move(&m));
+        assert_eq!(lines[4], "pong 3");
+    }
+
+    #[test]
+    fn hint() {
+        let output = serve_str("set depth 3\nhint 1\n");
+        let words: Vec<&str> = output.lines().nth(1).unwrap().split(' ').collect();
+        assert_eq!(words[0], "search");
+        assert!(Board::new(8).is_valid_move(&words[1].parse().unwrap()));
+        assert_eq!(words[4], "3");
+
+        let output = serve_str("set depth 2\nhint 4\n");
+        let hints: Vec<Vec<&str>> = output.lines().filter(|l| l.starts_with("search")).map(|l| l.split(' ').collect()).collect();
+        assert_eq!(hints.len(), 4);
+        let evals: Vec<i32> = hints.iter().map(|h| h[2].parse().unwrap()).collect();
+        assert!(evals.is_sorted_by(|a, b| a >= b));
+    }
+
+    #[test]
+    fn evals() {
+        assert_eq!(eval(Score::Exact(-12), 64), -12);
+        assert_eq!(eval(Score::Heuristic(7), 64), 7);
+        assert_eq!(eval(Score::Heuristic(i32::MAX), 64), 64);
+        assert_eq!(eval(Score::Heuristic(i32::MIN), 36), -36);
+    }
+
+    #[test]
+    fn errors() {
+        let output = serve_str("set game (;GM[Othello];)\nset depth deep\nmove A1\nmove PA\nhint all\n");
+        assert_eq!(output, "status invalid game: MissingField(\"BO\")\nstatus invalid depth: deep\nstatus illegal move: A1\nstatus invalid hint: all\n");
+    }
+}
@@ -0,0 +1,191 @@
+use crate::board::{Board, Move};
+
+use std::time::Instant;
+
+/// Count the leaves of the tree of the games from the position, to `depth` plies.
+/// A pass is a ply of its own, and a game that ends before `depth` is a leaf.
+pub fn perft(board: &Board, depth: u8) -> u64 {
+    if depth == 0 || board.player.is_none() {
+        return 1;
+    }
+    // Every move of the last ply is a leaf, whatever follows it.
+    if depth == 1 {
+        return board.move_count() as u64;
+    }
+    board.moves().iter().map(|m| after_move(board, m, depth - 1)).sum()
+}
+
+/// Count the leaves of the tree under each move of the position, as `perft` does, to find which
+/// one is wrong when a count is.
+pub fn split(board: &Board, depth: u8) -> Vec<(Move, u64)> {
+    if depth == 0 || board.player.is_none() {
+        return vec!();
+    }
+    board.moves().into_iter().map(|m| (m, after_move(board, &m, depth - 1))).collect()
+}
+
+// Count the leaves under the position reached by playing `m`, which `Board::play` may have made
+// skip a forced pass of the opponent.
+fn after_move(board: &Board, m: &Move, depth: u8) -> u64 {
+    let child = board.play(m).expect("moves of the board are valid");
+    match child.player {
+        Some(player) if Some(player) == board.player && depth > 0 => perft(&child, depth - 1),
+        _ => perft(&child, depth),
+    }
+}
+
+/// Print the counts of `split` and their total, with the time taken.
+pub fn run(board: &Board, depth: u8, split_counts: bool) {
+    let start = Instant::now();
+    let counts = if split_counts { split(board, depth) } else { vec!() };
+    for (m, count) in &counts {
+        println!("{}: {}", m, count);
+    }
+    let total = match counts.is_empty() {
+        true => perft(board, depth),
+        false => counts.iter().map(|(_, count)| count).sum(),
+    };
+    let elapsed = start.elapsed().as_secs_f64();
+    println!("Perft {}: {} leaves in {:.3} s ({:.0} leaves/s).", depth, total, elapsed, total as f64 / elapsed.max(f64::EPSILON));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        board::Player,
+        game::Game,
+    };
+
+    // Count the leaves like `perft` with the rules applied square by square on a grid, to check
+    // the move generation of `Board` against code that shares nothing with it.
+    fn reference(grid: &mut Vec<Vec<Option<Player>>>, player: Player, depth: u8, passed: bool) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let (width, height) = (grid[0].len() as i32, grid.len() as i32);
+        let mut count = 0;
+        let mut moved = false;
+        for y in 0..height {
+            for x in 0..width {
+                let flips = flips(grid, player, x, y);
+                if flips.is_empty() {
+                    continue;
+                }
+                moved = true;
+                let saved = grid.clone();
+                grid[y as usize][x as usize] = Some(player);
+                for (x, y) in flips {
+                    grid[y as usize][x as usize] = Some(player);
+                }
+                count += reference(grid, player.other(), depth - 1, false);
+                *grid = saved;
+            }
+        }
+        match (moved, passed) {
+            (true, _) => count,
+            // The previous player passed too, so the game is over.
+            (false, true) => 1,
+            (false, false) => {
+                // A game that is over is a leaf, not a pass.
+                if (0..height).all(|y| (0..width).all(|x| flips(grid, player.other(), x, y).is_empty())) {
+                    1
+                } else {
+                    reference(grid, player.other(), depth - 1, true)
+                }
+            },
+        }
+    }
+
+    fn flips(grid: &[Vec<Option<Player>>], player: Player, x: i32, y: i32) -> Vec<(i32, i32)> {
+        let (width, height) = (grid[0].len() as i32, grid.len() as i32);
+        let mut flips = vec!();
+        if grid[y as usize][x as usize].is_some() {
+            return flips;
+        }
+        for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+            let mut line = vec!();
+            let (mut i, mut j) = (x + dx, y + dy);
+            while (0..width).contains(&i) && (0..height).contains(&j) && grid[j as usize][i as usize] == Some(player.other()) {
+                line.push((i, j));
+                i += dx;
+                j += dy;
+            }
+            if !line.is_empty() && (0..width).contains(&i) && (0..height).contains(&j) && grid[j as usize][i as usize] == Some(player) {
+                flips.extend(line);
+            }
+        }
+        flips
+    }
+
+    fn grid(board: &Board) -> Vec<Vec<Option<Player>>> {
+        (0..board.height()).map(|y| (0..board.width()).map(|x| board.get(x, y).into()).collect()).collect()
+    }
+
+    #[test]
+    fn standard_board() {
+        let board = Board::new(8);
+        let counts: Vec<u64> = (1..=8).map(|depth| perft(&board, depth)).collect();
+        assert_eq!(counts, vec!(4, 12, 56, 244, 1396, 8200, 55092, 390216));
+        assert_eq!(perft(&board, 0), 1);
+        // The generic implementation of the board gives the same counts.
+        assert_eq!(perft(&board.to_generic(), 6), 8200);
+    }
+
+    #[test]
+    fn small_boards() {
+        // The 4x4 game always ends within 16 plies, and there are 60060 complete games.
+        let counts: Vec<u64> = (1..=16).map(|depth| perft(&Board::new(4), depth)).collect();
+        assert_eq!(counts, vec!(4, 12, 44, 128, 424, 1256, 3624, 9116, 20044, 36540, 50704, 57436, 59564, 59980, 60060, 60060));
+        assert_eq!(perft(&Board::new(4), 30), 60060);
+        let counts: Vec<u64> = (1..=7).map(|depth| perft(&Board::new(6), depth)).collect();
+        assert_eq!(counts, vec!(4, 12, 56, 244, 1364, 7604, 47740));
+        assert_eq!(perft(&Board::new(2), 5), 1);
+    }
+
+    #[test]
+    fn against_reference() {
+        for (size, depth) in [(4, 16), (6, 6)] {
+            let board = Board::new(size);
+            assert_eq!(perft(&board, depth), reference(&mut grid(&board), Player::Black, depth, false), "{}x{}", size, depth);
+        }
+        // Larger, rectangular and odd boards, which have their cells on two words.
+        for (width, height, depth) in [(14, 14, 5), (8, 10, 5), (6, 4, 8), (7, 5, 6)] {
+            let board = Board::rectangular(width, height);
+            assert_eq!(perft(&board, depth), reference(&mut grid(&board), Player::Black, depth, false), "{}x{}", width, height);
+            assert_eq!(perft(&board, depth), perft(&Board::rectangular(height, width), depth));
+        }
+        // Positions with passes and early ends, reached by playing the first move every time.
+        let mut game = Game::new(Board::new(6));
+        while let Some(&m) = game.board().moves().first() {
+            game.play(&m);
+            let board = game.board();
+            if let Some(player) = board.player {
+                assert_eq!(perft(board, 4), reference(&mut grid(board), player, 4, false), "{}", game.sequence());
+            }
+        }
+    }
+
+    #[test]
+    fn passes() {
+        // White can play A3, B4, C4 or D4, and black has to pass after A3, so the pass is the only
+        // leaf under A3 at depth 2, then white has 4 moves.
+        let mut game = Game::new(Board::new(4));
+        game.play_sequence("b1a1d3c1a2").unwrap();
+        let board = game.board();
+        assert_eq!(board.player, Some(Player::White));
+        let moves = |depth| -> Vec<(String, u64)> {
+            split(board, depth).into_iter().map(|(m, count)| (m.to_string(), count)).collect()
+        };
+        assert_eq!(moves(1), vec!(("A3".into(), 1), ("B4".into(), 1), ("C4".into(), 1), ("D4".into(), 1)));
+        assert_eq!(moves(2)[0], ("A3".into(), 1));
+        assert_eq!(moves(3)[0], ("A3".into(), 4));
+        assert!(split(&board.play(&"A3".parse().unwrap()).unwrap(), 0).is_empty());
+
+        // Splits add up to the perft of the position.
+        let splits = split(&Board::new(8), 5);
+        assert_eq!(splits.len(), 4);
+        assert!(splits.iter().all(|&(_, count)| count == 349));
+        assert_eq!(splits.iter().map(|(_, count)| count).sum::<u64>(), perft(&Board::new(8), 5));
+    }
+}
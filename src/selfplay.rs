@@ -0,0 +1,365 @@
+use crate::{
+    board::{Board, ParsingError, Player, Square},
+    computer::{self, Example, Score, SearchOptions},
+    game::Game,
+};
+
+use std::{
+    fmt,
+    fs::write,
+    path::PathBuf,
+    str::FromStr,
+    sync::{atomic::{AtomicUsize, Ordering}, mpsc::channel},
+    thread,
+};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::IndexedRandom};
+
+/// The number of bytes of a sample in the binary format.
+pub const SAMPLE_SIZE: usize = 40;
+// Samples hold their discs on 128 bits, so they are limited to square boards of size at most 10.
+const MAX_SIZE: u8 = 10;
+
+/// A position of a self-play game, labeled with the outcome of the game and its search score.
+// In the binary format, a sample is written as its size, its player (0 for black, 1 for white),
+// whether its score is exact (0 or 1), its result (as a signed byte), its score (4 bytes), then
+// the discs of black and white (16 bytes each), all numbers being little-endian.
+// In the text format, it is written on a line as `<size> <player> <black> <white> <result>
+// <score> <exact|heuristic>`, the player being `X` or `O` and discs in hexadecimal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub size: u8,
+    /// The discs of each player, the square at column `x` and row `y` being the bit
+    /// `x + y * size`.
+    pub black: u128,
+    pub white: u128,
+    /// The player to move.
+    pub player: Player,
+    /// The final disc difference of the game, from the point of view of `player`.
+    pub result: i8,
+    /// The score of the search of the position, from the point of view of `player`.
+    pub score: i32,
+    /// Whether the score is the final disc difference with perfect play, rather than a heuristic
+    /// evaluation.
+    pub exact: bool,
+}
+
+/// The settings of self-play.
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub games: usize,
+    pub size: u8,
+    /// The depth of the searches choosing the moves and scoring the positions.
+    pub depth: u8,
+    /// The probability of playing a random move instead of the best one.
+    pub random_rate: f64,
+    /// The number of random plies played before recording positions.
+    pub opening_plies: usize,
+    pub seed: u64,
+    pub threads: usize,
+    /// The file the positions are written to.
+    pub output: PathBuf,
+    /// Whether to write the positions in the text format rather than the binary one.
+    pub text: bool,
+}
+
+impl Sample {
+    /// Label a position, which must have a player to move.
+    /// Return `BadSize` for boards that are not square of a size samples support.
+    pub fn new(board: &Board, result: i8, score: Score<i32>) -> Result<Self, ParsingError> {
+        if board.width() != board.height() || !is_valid_size(board.width()) {
+            return Err(ParsingError::BadSize);
+        }
+        let (mut black, mut white) = (0, 0);
+        let size = board.width();
+        for y in 0..size {
+            for x in 0..size {
+                let bit = 1 << (x as u32 + y as u32 * size as u32);
+                match board.get(x, y) {
+                    Square::Disc(Player::Black) => black |= bit,
+                    Square::Disc(Player::White) => white |= bit,
+                    Square::Empty => { },
+                }
+            }
+        }
+        let (score, exact) = match score {
+            Score::Heuristic(score) => (score, false),
+            Score::Exact(score) => (score, true),
+        };
+        let player = board.player.expect("samples have a player to move");
+        Ok(Self { size, black, white, player, result, score, exact })
+    }
+
+    /// Return the position of the sample.
+    pub fn board(&self) -> Board {
+        let mut text = String::from(char::from(self.player));
+        for y in 0..self.size {
+            text.push('\n');
+            for x in 0..self.size {
+                let bit = 1 << (x as u32 + y as u32 * self.size as u32);
+                text.push(if self.black & bit != 0 {
+                    'X'
+                } else if self.white & bit != 0 {
+                    'O'
+                } else { '_' });
+            }
+        }
+        text.parse().expect("samples hold valid boards")
+    }
+
+    /// Return the sample as a training example, targeting its exact score if known, and the final
+    /// disc difference of its game otherwise.
+    pub fn example(&self) -> Example {
+        let target = if self.exact { self.score as f32 } else { self.result as f32 };
+        Example { board: self.board(), player: self.player, target }
+    }
+
+    /// Return the binary representation of the sample.
+    pub fn to_bytes(&self) -> [u8; SAMPLE_SIZE] {
+        let mut bytes = [0; SAMPLE_SIZE];
+        bytes[0] = self.size;
+        bytes[1] = (self.player == Player::White) as u8;
+        bytes[2] = self.exact as u8;
+        bytes[3] = self.result as u8;
+        bytes[4..8].copy_from_slice(&self.score.to_le_bytes());
+        bytes[8..24].copy_from_slice(&self.black.to_le_bytes());
+        bytes[24..40].copy_from_slice(&self.white.to_le_bytes());
+        bytes
+    }
+
+    /// Read a sample from its binary representation.
+    pub fn from_bytes(bytes: &[u8; SAMPLE_SIZE]) -> Result<Self, ParsingError> {
+        let sample = Self {
+            size: bytes[0],
+            player: match bytes[1] {
+                0 => Player::Black,
+                1 => Player::White,
+                _ => return Err(ParsingError::Generic),
+            },
+            exact: bytes[2] != 0,
+            result: bytes[3] as i8,
+            score: i32::from_le_bytes(bytes[4..8].try_into().expect("slice of 4 bytes")),
+            black: u128::from_le_bytes(bytes[8..24].try_into().expect("slice of 16 bytes")),
+            white: u128::from_le_bytes(bytes[24..40].try_into().expect("slice of 16 bytes")),
+        };
+        sample.check()
+    }
+
+    // Check that the size is valid and that the discs are on the board and not on each other.
+    fn check(self) -> Result<Self, ParsingError> {
+        if !is_valid_size(self.size) {
+            return Err(ParsingError::BadSize);
+        }
+        let squares = self.size as u32 * self.size as u32;
+        if self.black & self.white != 0 || (self.black | self.white).checked_shr(squares).is_some_and(|b| b != 0) {
+            return Err(ParsingError::InconsistentSize);
+        }
+        Ok(self)
+    }
+}
+
+impl fmt::Display for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {:x} {:x} {} {} {}", self.size, char::from(self.player), self.black, self.white,
+            self.result, self.score, if self.exact { "exact" } else { "heuristic" })
+    }
+}
+
+impl FromStr for Sample {
+    type Err = ParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [size, player, black, white, result, score, exact] = fields[..] else {
+            return Err(ParsingError::Generic);
+        };
+        let sample = Self {
+            size: size.parse().map_err(|_| ParsingError::BadSize)?,
+            player: match player {
+                "X" => Player::Black,
+                "O" => Player::White,
+                _ => return Err(ParsingError::PlayerParseError(player.chars().next().unwrap_or(' '))),
+            },
+            black: u128::from_str_radix(black, 16).map_err(|_| ParsingError::Generic)?,
+            white: u128::from_str_radix(white, 16).map_err(|_| ParsingError::Generic)?,
+            result: result.parse().map_err(|_| ParsingError::Generic)?,
+            score: score.parse().map_err(|_| ParsingError::Generic)?,
+            exact: match exact {
+                "exact" => true,
+                "heuristic" => false,
+                _ => return Err(ParsingError::Generic),
+            },
+        };
+        sample.check()
+    }
+}
+
+fn is_valid_size(size: u8) -> bool {
+    (2..=MAX_SIZE).contains(&size)
+}
+
+/// Read the samples of a file in the binary format.
+pub fn read(bytes: &[u8]) -> Result<Vec<Sample>, ParsingError> {
+    if !bytes.len().is_multiple_of(SAMPLE_SIZE) {
+        return Err(ParsingError::UnterminatedRecord);
+    }
+    bytes.chunks(SAMPLE_SIZE)
+        .map(|chunk| Sample::from_bytes(chunk.try_into().expect("chunks have the size of samples")))
+        .collect()
+}
+
+/// Read the samples of a file in the text format, one per line.
+pub fn read_text(text: &str) -> Result<Vec<Sample>, ParsingError> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Play a game against itself, and return its positions from the end of the opening on.
+/// Games are determined by the seed and their index.
+pub fn play(options: &Options, index: usize) -> Result<Vec<Sample>, ParsingError> {
+    if !is_valid_size(options.size) {
+        return Err(ParsingError::BadSize);
+    }
+    let mut rng = StdRng::seed_from_u64(options.seed ^ (index as u64).wrapping_mul(0x9e3779b97f4a7c15));
+    let mut game = Game::new(Board::new(options.size));
+    for _ in 0..options.opening_plies {
+        let Some(&m) = game.board().moves().choose(&mut rng) else { break; };
+        game.play(&m);
+    }
+
+    let mut positions = vec!();
+    while let Some(player) = game.board().player {
+        let search_options = SearchOptions {
+            timeout: None,
+            depth: Some(options.depth),
+            seed: Some(rng.random()),
+            ..Default::default()
+        };
+        let report = computer::search(game.board(), &search_options);
+        positions.push((game.board().clone(), player, report.score.expect("searches without timeout complete")));
+
+        let m = if rng.random_bool(options.random_rate) {
+            *game.board().moves().choose(&mut rng).expect("the player to move has moves")
+        } else {
+            report.best_move.expect("the player to move has moves")
+        };
+        game.play(&m);
+    }
+
+    let (black, white) = game.board().score();
+    let difference = black as i8 - white as i8;
+    positions.into_iter().map(|(board, player, score)| {
+        let result = match player {
+            Player::Black => difference,
+            Player::White => -difference,
+        };
+        Sample::new(&board, result, score)
+    }).collect()
+}
+
+/// Play the games on several threads, and write their positions to the output file.
+pub fn run(options: &Options) -> Result<Vec<Sample>, ParsingError> {
+    let next = AtomicUsize::new(0);
+    let mut games = vec!();
+    thread::scope(|scope| {
+        let (sender, receiver) = channel();
+        for _ in 0..options.threads.max(1) {
+            let (sender, next) = (sender.clone(), &next);
+            scope.spawn(move || {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= options.games || sender.send((i, play(options, i))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        games.extend(receiver);
+    });
+    games.sort_by_key(|(i, _)| *i);
+    let games = games.into_iter().map(|(_, samples)| samples).collect::<Result<Vec<_>, _>>()?;
+    let samples: Vec<Sample> = games.into_iter().flatten().collect();
+
+    let content = if options.text {
+        samples.iter().map(|s| format!("{}\n", s)).collect::<String>().into_bytes()
+    } else {
+        samples.iter().flat_map(Sample::to_bytes).collect()
+    };
+    write(&options.output, content)?;
+    println!("{} games played, {} positions written.", options.games, samples.len());
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{read as read_file, read_to_string};
+
+    fn options(output: PathBuf) -> Options {
+        Options { games: 4, size: 6, depth: 2, random_rate: 0.2, opening_plies: 2, seed: 7, threads: 2, output, text: false }
+    }
+
+    #[test]
+    fn formats() {
+        let mut board = Board::new(8);
+        board = board.play(&board.moves()[0]).unwrap();
+        let sample = Sample::new(&board, -12, Score::Heuristic(3)).unwrap();
+        assert_eq!(sample.player, Player::White);
+        assert_eq!(sample.board(), board);
+        assert_eq!(Sample::from_bytes(&sample.to_bytes()), Ok(sample));
+        assert_eq!(sample.to_string().parse(), Ok(sample));
+
+        let exact = Sample::new(&Board::new(4), 2, Score::Exact(-2)).unwrap();
+        assert!(exact.exact);
+        assert_eq!(exact.to_string(), "4 X 240 420 2 -2 exact");
+        assert_eq!(exact.to_string().parse(), Ok(exact));
+        assert_eq!(exact.example().target, -2.0);
+        assert_eq!(sample.example(), Example { board, player: Player::White, target: -12.0 });
+
+        let mut bytes = exact.to_bytes();
+        bytes[0] = 11;
+        assert_eq!(Sample::from_bytes(&bytes), Err(ParsingError::BadSize));
+        assert_eq!("4 X 1ffff 0 0 0 exact".parse::<Sample>(), Err(ParsingError::InconsistentSize));
+        assert_eq!("4 X 1 1 0 0 exact".parse::<Sample>(), Err(ParsingError::InconsistentSize));
+        assert_eq!(read(&bytes[1..]), Err(ParsingError::UnterminatedRecord));
+
+        // Positions are held on 128 bits, which only fit square boards of size at most 10.
+        assert_eq!(Sample::new(&Board::new(12), 0, Score::Exact(0)), Err(ParsingError::BadSize));
+        assert_eq!(Sample::new(&Board::rectangular(8, 6), 0, Score::Exact(0)), Err(ParsingError::BadSize));
+    }
+
+    #[test]
+    fn labels() {
+        let samples = play(&options(PathBuf::new()), 0).unwrap();
+        assert_eq!(play(&Options { size: 12, ..options(PathBuf::new()) }, 0), Err(ParsingError::BadSize));
+        assert!(!samples.is_empty());
+        for (sample, next) in samples.iter().zip(&samples[1..]) {
+            // The result of every position is the same for black.
+            let sign = |s: &Sample| if s.player == Player::Black { 1 } else { -1 };
+            assert_eq!(sign(sample) * sample.result, sign(next) * next.result);
+            assert!(sample.board().player.is_some());
+        }
+        // The last positions are solved exactly.
+        assert!(samples.last().unwrap().exact);
+    }
+
+    #[test]
+    fn determinism() {
+        let directory = tempfile::tempdir().unwrap();
+        let (binary, text) = (directory.path().join("samples.bin"), directory.path().join("samples.txt"));
+        let samples = run(&options(binary.clone())).unwrap();
+        let mut single = options(text.clone());
+        single.threads = 1;
+        single.text = true;
+        assert_eq!(run(&single).unwrap(), samples);
+
+        assert_eq!(read(&read_file(&binary).unwrap()), Ok(samples.clone()));
+        assert_eq!(read_text(&read_to_string(&text).unwrap()), Ok(samples.clone()));
+
+        let mut other = options(binary);
+        other.seed = 8;
+        assert_ne!(run(&other).unwrap(), samples);
+    }
+}
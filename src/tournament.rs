@@ -0,0 +1,637 @@
+use crate::{
+    Computer, Tactic,
+    board::{Board, ParsingError, Player},
+    computer::{Mcts, MctsOptions, Patterns, Playout, SearchOptions},
+    engine::Engine,
+    game::Game,
+};
+
+use std::{
+    fmt::Write as _,
+    fs::{read_to_string, write},
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::channel,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use rand::{SeedableRng, rngs::StdRng, seq::IndexedRandom};
+
+// The error rates of the SPRT, for both hypotheses.
+const SPRT_ALPHA: f64 = 0.05;
+const SPRT_BETA: f64 = 0.05;
+// The quantile of the normal distribution for 95% confidence intervals.
+const Z_95: f64 = 1.96;
+
+/// A participant of a tournament: `random`, `ai`, `ai:<depth>`, `patterns:<weights file>`,
+/// `mcts`, `mcts:<exploration>`, `mcts:<exploration>:<random|light>` or `engine:<command>`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Participant {
+    Random,
+    Computer(Option<u8>),
+    Patterns(PathBuf, Arc<Patterns>),
+    Mcts(f64, Playout),
+    External(String),
+}
+
+/// The settings of a tournament.
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub participants: Vec<Participant>,
+    pub size: u8,
+    /// The time each player has for each move.
+    pub time: Duration,
+    /// The number of random openings, when no book is given.
+    pub openings: usize,
+    /// The number of random plies of each random opening.
+    pub opening_plies: usize,
+    /// A file with an opening per line, as a sequence of moves.
+    pub book: Option<PathBuf>,
+    pub threads: usize,
+    pub seed: Option<u64>,
+    /// The Elo differences of the null and alternative hypotheses of the SPRT, which stops the
+    /// tournament once one of them is accepted for the first participant against the second.
+    pub sprt: Option<(f64, f64)>,
+    pub csv: Option<PathBuf>,
+    pub json: Option<PathBuf>,
+}
+
+/// The result of a game of the tournament.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub opening: usize,
+    /// The indices of the participants playing black and white.
+    pub black: usize,
+    pub white: usize,
+    pub discs: (u8, u8),
+    /// The player who forfeited, by crashing, running out of time or playing an illegal move.
+    pub forfeit: Option<Player>,
+    pub plies: usize,
+}
+
+/// The wins, draws and losses of a participant against another.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Record {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+/// The state of a sequential probability ratio test, stopping once the log-likelihood ratio
+/// leaves the bounds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sprt {
+    Continue,
+    /// The null hypothesis was accepted: the Elo difference is at most the lower one.
+    H0,
+    /// The alternative hypothesis was accepted: the Elo difference is at least the upper one.
+    H1,
+}
+
+impl FromStr for Participant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "random" => Ok(Self::Random),
+            None if s == "ai" => Ok(Self::Computer(None)),
+            None if s == "mcts" => {
+                let options = MctsOptions::default();
+                Ok(Self::Mcts(options.exploration, options.playout))
+            },
+            Some(("mcts", settings)) => {
+                let (exploration, playout) = settings.split_once(':').unwrap_or((settings, "light"));
+                let exploration = match exploration.parse() {
+                    Ok(c) if c >= 0.0 => c,
+                    _ => return Err(format!("invalid exploration constant '{}'", exploration)),
+                };
+                match playout {
+                    "random" => Ok(Self::Mcts(exploration, Playout::Random)),
+                    "light" => Ok(Self::Mcts(exploration, Playout::Light)),
+                    _ => Err(format!("invalid playout '{}'", playout)),
+                }
+            },
+            Some(("ai", depth)) => match depth.parse() {
+                Ok(depth) if depth > 0 => Ok(Self::Computer(Some(depth))),
+                _ => Err(format!("invalid depth '{}'", depth)),
+            },
+            Some(("patterns", file)) => match Patterns::load(file) {
+                Ok(patterns) => Ok(Self::Patterns(file.into(), Arc::new(patterns))),
+                Err(e) => Err(format!("could not load weights '{}': {:?}", file, e)),
+            },
+            Some(("engine", command)) if !command.trim().is_empty() => Ok(Self::External(command.to_string())),
+            _ => Err("expected 'random', 'ai', 'ai:<depth>', 'patterns:<file>', 'mcts', 'mcts:<exploration>[:random|light]' or 'engine:<command>'".into()),
+        }
+    }
+}
+
+impl Participant {
+    /// Return the name of the participant, as given on the command line.
+    pub fn name(&self) -> String {
+        match self {
+            Self::Random => "random".into(),
+            Self::Computer(None) => "ai".into(),
+            Self::Computer(Some(depth)) => format!("ai:{}", depth),
+            Self::Patterns(file, _) => format!("patterns:{}", file.display()),
+            Self::Mcts(exploration, Playout::Light) if *exploration == MctsOptions::default().exploration => "mcts".into(),
+            Self::Mcts(exploration, Playout::Light) => format!("mcts:{}", exploration),
+            Self::Mcts(exploration, Playout::Random) => format!("mcts:{}:random", exploration),
+            Self::External(command) => format!("engine:{}", command),
+        }
+    }
+
+    fn tactic(&self) -> std::io::Result<Tactic> {
+        Ok(match self {
+            Self::Random => Tactic::Random,
+            Self::Computer(depth) => Tactic::Computer(Computer {
+                options: SearchOptions { depth: *depth, ..Default::default() },
+                ..Default::default()
+            }),
+            Self::Patterns(_, patterns) => Tactic::Computer(Computer { patterns: Some(patterns.clone()), ..Default::default() }),
+            Self::Mcts(exploration, playout) => {
+                Tactic::Mcts(Box::new(Mcts::new(MctsOptions { exploration: *exploration, playout: *playout, ..Default::default() })))
+            },
+            Self::External(command) => Tactic::External(Engine::spawn(command)?),
+        })
+    }
+}
+
+impl Outcome {
+    /// Return the score of black: 1 for a win, 0.5 for a draw and 0 for a loss.
+    pub fn score(&self) -> f64 {
+        match self.forfeit {
+            Some(Player::Black) => 0.0,
+            Some(Player::White) => 1.0,
+            None => match self.discs.0.cmp(&self.discs.1) {
+                std::cmp::Ordering::Greater => 1.0,
+                std::cmp::Ordering::Equal => 0.5,
+                std::cmp::Ordering::Less => 0.0,
+            },
+        }
+    }
+}
+
+impl Record {
+    fn add(&mut self, score: f64) {
+        if score > 0.5 {
+            self.wins += 1;
+        } else if score < 0.5 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+
+    /// Return the number of games.
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    // Return the mean score per game and its variance.
+    fn score(&self) -> (f64, f64) {
+        let n = self.games() as f64;
+        let mean = (self.wins as f64 + self.draws as f64 / 2.0) / n;
+        let variance = (self.wins as f64 * (1.0 - mean).powi(2)
+            + self.draws as f64 * (0.5 - mean).powi(2)
+            + self.losses as f64 * mean.powi(2)) / n;
+        (mean, variance)
+    }
+
+    /// Return the estimated Elo difference, with the bounds of its 95% confidence interval, or
+    /// `None` without any game.
+    /// Perfect scores, and bounds past them, give infinite differences.
+    pub fn elo(&self) -> Option<(f64, f64, f64)> {
+        if self.games() == 0 {
+            return None;
+        }
+        let (mean, variance) = self.score();
+        let margin = Z_95 * (variance / self.games() as f64).sqrt();
+        let bound = |score: f64| elo(score.clamp(0.0, 1.0));
+        Some((elo(mean), bound(mean - margin), bound(mean + margin)))
+    }
+
+    /// Return the log-likelihood ratio of the Elo difference being `elo1` rather than `elo0`,
+    /// with the normal approximation of the scores.
+    pub fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        let (mean, variance) = self.score();
+        // Records with a single kind of result have no variance, so a draw is added to them.
+        if variance == 0.0 {
+            return Record { draws: self.draws + 1, ..*self }.llr(elo0, elo1);
+        }
+        let (s0, s1) = (expected_score(elo0), expected_score(elo1));
+        (s1 - s0) * (2.0 * mean - s0 - s1) * self.games() as f64 / (2.0 * variance)
+    }
+
+    /// Return the state of the SPRT of `elo0` against `elo1`.
+    pub fn sprt(&self, elo0: f64, elo1: f64) -> Sprt {
+        let llr = self.llr(elo0, elo1);
+        let (lower, upper) = sprt_bounds();
+        if llr >= upper {
+            Sprt::H1
+        } else if llr <= lower {
+            Sprt::H0
+        } else {
+            Sprt::Continue
+        }
+    }
+}
+
+/// Play the tournament, printing the games as they end, then the results of each pair.
+pub fn run(options: &Options) -> Result<Vec<Outcome>, ParsingError> {
+    let openings = match &options.book {
+        Some(book) => read_book(&read_to_string(book)?, options.size)?,
+        None => random_openings(options),
+    };
+
+    // Each pair plays each opening twice, swapping colors.
+    let count = options.participants.len();
+    let mut schedule = vec!();
+    for opening in 0..openings.len() {
+        for (a, b) in pairs(count) {
+            schedule.push((opening, a, b));
+            schedule.push((opening, b, a));
+        }
+    }
+    println!("{} participants, {} openings, {} games.", count, openings.len(), schedule.len());
+
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let mut outcomes = vec!();
+    let mut records = vec![vec![Record::default(); count]; count];
+    thread::scope(|scope| {
+        let (sender, receiver) = channel();
+        for _ in 0..options.threads.max(1) {
+            let sender = sender.clone();
+            let (next, stop, schedule, openings) = (&next, &stop, &schedule, &openings);
+            scope.spawn(move || {
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= schedule.len() || stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let (opening, black, white) = schedule[i];
+                    let outcome = play(&openings[opening], opening, black, white, options);
+                    if sender.send((i, outcome)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        for (i, outcome) in receiver {
+            let names = (options.participants[outcome.black].name(), options.participants[outcome.white].name());
+            let forfeit = match outcome.forfeit {
+                Some(player) => format!(", {} forfeits", String::from(player)),
+                None => String::new(),
+            };
+            println!("Game {}: {} (black) {}-{} {} (white){}.", i + 1, names.0, outcome.discs.0, outcome.discs.1, names.1, forfeit);
+
+            records[outcome.black][outcome.white].add(outcome.score());
+            records[outcome.white][outcome.black].add(1.0 - outcome.score());
+            if let Some((elo0, elo1)) = options.sprt {
+                if records[0][1].sprt(elo0, elo1) != Sprt::Continue {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            }
+            outcomes.push((i, outcome));
+        }
+    });
+    outcomes.sort_by_key(|(i, _)| *i);
+    let outcomes: Vec<Outcome> = outcomes.into_iter().map(|(_, o)| o).collect();
+
+    // Pairs left without games by an early stop are not reported.
+    println!();
+    for (a, b) in pairs(count) {
+        let record = records[a][b];
+        let Some((elo, low, high)) = record.elo() else { continue; };
+        println!("{} vs {}: {} wins, {} draws, {} losses, Elo {:+.1} (95% CI [{:+.1}, {:+.1}]).",
+            options.participants[a].name(), options.participants[b].name(),
+            record.wins, record.draws, record.losses, elo, low, high);
+    }
+    if let Some((elo0, elo1)) = options.sprt {
+        let (lower, upper) = sprt_bounds();
+        let state = match records[0][1].sprt(elo0, elo1) {
+            Sprt::Continue => "no hypothesis accepted",
+            Sprt::H0 => "H0 accepted",
+            Sprt::H1 => "H1 accepted",
+        };
+        println!("SPRT [{}, {}]: LLR {:.2} ({:.2}, {:.2}), {}.", elo0, elo1, records[0][1].llr(elo0, elo1), lower, upper, state);
+    }
+
+    if let Some(file) = &options.csv {
+        write(file, csv(&options.participants, &outcomes))?;
+    }
+    if let Some(file) = &options.json {
+        write(file, json(&options.participants, &outcomes, &records))?;
+    }
+    Ok(outcomes)
+}
+
+// Play a game from an opening without printing it.
+// A participant that cannot be started forfeits.
+fn play(opening: &Game, index: usize, black: usize, white: usize, options: &Options) -> Outcome {
+    let mut game = opening.clone();
+    let mut tactics = [black, white].map(|p| options.participants[p].tactic());
+
+    let mut forfeit = None;
+    while let Some(player) = game.board().player {
+        let tactic = match player {
+            Player::Black => &mut tactics[0],
+            Player::White => &mut tactics[1],
+        };
+        let start = Instant::now();
+        let Some(m) = tactic.as_mut().ok().and_then(|t| t.choose_move(&mut game, options.time, false)) else {
+            forfeit = Some(player);
+            break;
+        };
+        game.play_timed(&m, start.elapsed());
+    }
+
+    Outcome {
+        opening: index,
+        black,
+        white,
+        discs: game.board().score(),
+        forfeit,
+        plies: game.ply(),
+    }
+}
+
+// Parse a book of openings, one sequence of moves per line, ignoring empty lines and comments.
+fn read_book(book: &str, size: u8) -> Result<Vec<Game>, ParsingError> {
+    let mut openings = vec!();
+    for line in book.lines() {
+        let line = line.split('#').next().expect("split returns at least one item").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut game = Game::new(Board::new(size));
+        game.play_sequence(line)?;
+        openings.push(game);
+    }
+    if openings.is_empty() {
+        return Err(ParsingError::EmptyFile);
+    }
+    Ok(openings)
+}
+
+// Generate openings by playing random moves, from a seed for reproducible tournaments.
+fn random_openings(options: &Options) -> Vec<Game> {
+    let mut rng = match options.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_rng(&mut rand::rng()),
+    };
+    (0..options.openings.max(1)).map(|_| {
+        let mut game = Game::new(Board::new(options.size));
+        for _ in 0..options.opening_plies {
+            let Some(&m) = game.board().moves().choose(&mut rng) else { break; };
+            game.play(&m);
+        }
+        game
+    }).collect()
+}
+
+// Return every pair of distinct participants, the first having the lower index.
+fn pairs(count: usize) -> Vec<(usize, usize)> {
+    (0..count).flat_map(|a| (a + 1..count).map(move |b| (a, b))).collect()
+}
+
+fn elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+fn sprt_bounds() -> (f64, f64) {
+    ((SPRT_BETA / (1.0 - SPRT_ALPHA)).ln(), ((1.0 - SPRT_BETA) / SPRT_ALPHA).ln())
+}
+
+fn csv(participants: &[Participant], outcomes: &[Outcome]) -> String {
+    let mut out = String::from("game,opening,black,white,black_discs,white_discs,forfeit,plies,black_score\n");
+    for (i, o) in outcomes.iter().enumerate() {
+        let forfeit = o.forfeit.map_or(String::new(), String::from);
+        writeln!(out, "{},{},{},{},{},{},{},{},{}", i + 1, o.opening,
+            csv_field(&participants[o.black].name()), csv_field(&participants[o.white].name()),
+            o.discs.0, o.discs.1, forfeit, o.plies, o.score()).expect("writing to a string");
+    }
+    out
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn json(participants: &[Participant], outcomes: &[Outcome], records: &[Vec<Record>]) -> String {
+    let names: Vec<String> = participants.iter().map(|p| json_string(&p.name())).collect();
+    let games: Vec<String> = outcomes.iter().map(|o| format!(
+        "{{\"opening\": {}, \"black\": {}, \"white\": {}, \"black_discs\": {}, \"white_discs\": {}, \"forfeit\": {}, \"plies\": {}, \"black_score\": {}}}",
+        o.opening, names[o.black], names[o.white], o.discs.0, o.discs.1,
+        o.forfeit.map_or("null".into(), |p| json_string(&String::from(p))), o.plies, o.score(),
+    )).collect();
+
+    let pairs: Vec<String> = pairs(participants.len()).into_iter().filter_map(|(a, b)| {
+        let record = records[a][b];
+        let (elo, low, high) = record.elo()?;
+        Some(format!(
+            "{{\"first\": {}, \"second\": {}, \"wins\": {}, \"draws\": {}, \"losses\": {}, \"elo\": {}, \"elo_low\": {}, \"elo_high\": {}}}",
+            names[a], names[b], record.wins, record.draws, record.losses, json_number(elo), json_number(low), json_number(high),
+        ))
+    }).collect();
+
+    format!("{{\n\"participants\": [{}],\n\"pairs\": [\n{}\n],\n\"games\": [\n{}\n]\n}}\n",
+        names.join(", "), pairs.join(",\n"), games.join(",\n"))
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).expect("writing to a string"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// JSON has no infinite numbers.
+fn json_number(x: f64) -> String {
+    if x.is_finite() { format!("{:.2}", x) } else { "null".into() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(participants: &str) -> Options {
+        Options {
+            participants: participants.split(' ').map(|p| p.parse().unwrap()).collect(),
+            size: 4,
+            time: Duration::from_millis(50),
+            openings: 3,
+            opening_plies: 2,
+            book: None,
+            threads: 2,
+            seed: Some(1),
+            sprt: None,
+            csv: None,
+            json: None,
+        }
+    }
+
+    fn record(wins: u32, draws: u32, losses: u32) -> Record {
+        Record { wins, draws, losses }
+    }
+
+    #[test]
+    fn participants() {
+        assert_eq!("random".parse(), Ok(Participant::Random));
+        assert_eq!("ai".parse(), Ok(Participant::Computer(None)));
+        assert_eq!("ai:3".parse(), Ok(Participant::Computer(Some(3))));
+        assert_eq!("engine:./run --fast".parse(), Ok(Participant::External("./run --fast".into())));
+        assert_eq!("mcts:0.5".parse(), Ok(Participant::Mcts(0.5, Playout::Light)));
+        assert_eq!("mcts:2:random".parse(), Ok(Participant::Mcts(2.0, Playout::Random)));
+        assert_eq!(Participant::Mcts(2.0, Playout::Random).name(), "mcts:2:random");
+        assert_eq!("mcts".parse::<Participant>().unwrap().name(), "mcts");
+        for p in ["human", "ai:0", "ai:deep", "engine:", "randoms", "patterns:no-such-file", "mcts:-1", "mcts:1:heavy"] {
+            assert!(p.parse::<Participant>().is_err(), "{}", p);
+        }
+        assert_eq!(Participant::Computer(Some(3)).name(), "ai:3");
+
+        let directory = tempfile::tempdir().unwrap();
+        let file = directory.path().join("weights.bin");
+        Patterns::new(4, 1).save(&file).unwrap();
+        let name = format!("patterns:{}", file.display());
+        let participant: Participant = name.parse().unwrap();
+        assert_eq!(participant.name(), name);
+    }
+
+    #[test]
+    fn elo_estimation() {
+        let (elo, low, high) = record(10, 0, 10).elo().unwrap();
+        assert_eq!(elo, 0.0);
+        assert!(low < 0.0 && high > 0.0);
+        assert!((record(75, 0, 25).elo().unwrap().0 - 190.85).abs() < 0.01);
+        assert!((record(50, 50, 0).elo().unwrap().0 - 190.85).abs() < 0.01);
+        assert_eq!(record(3, 0, 0).elo().unwrap().0, f64::INFINITY);
+        assert_eq!(record(0, 0, 0).elo(), None);
+
+        // Bounds past a perfect score are clamped to it.
+        let (elo, low, high) = record(9, 0, 1).elo().unwrap();
+        assert!(elo.is_finite() && low.is_finite());
+        assert_eq!(high, f64::INFINITY);
+        let (_, low, high) = record(1, 0, 9).elo().unwrap();
+        assert_eq!(low, f64::NEG_INFINITY);
+        assert!(high.is_finite());
+
+        // More games give narrower intervals.
+        let (_, low, high) = record(100, 0, 100).elo().unwrap();
+        let (_, narrow_low, narrow_high) = record(10, 0, 10).elo().unwrap();
+        assert!(high - low < narrow_high - narrow_low);
+    }
+
+    #[test]
+    fn sprt() {
+        assert_eq!(record(10, 0, 10).sprt(0.0, 10.0), Sprt::Continue);
+        assert_eq!(record(600, 0, 400).sprt(0.0, 10.0), Sprt::H1);
+        assert_eq!(record(400, 0, 600).sprt(0.0, 10.0), Sprt::H0);
+        assert!(record(60, 0, 40).llr(0.0, 10.0) > 0.0);
+        assert_eq!(record(0, 0, 0).sprt(0.0, 10.0), Sprt::Continue);
+        assert_eq!(record(20, 0, 0).sprt(0.0, 10.0), Sprt::H1);
+        assert_eq!(record(0, 0, 20).sprt(0.0, 10.0), Sprt::H0);
+    }
+
+    #[test]
+    fn openings() {
+        let book = "# Some openings\nf5d6\n\nf5 f6 # another one\n";
+        let openings = read_book(book, 8).unwrap();
+        assert_eq!(openings.len(), 2);
+        assert_eq!(openings[1].sequence(), "f5f6");
+        assert_eq!(read_book("f5a1", 8), Err(ParsingError::IllegalMove(2)));
+        assert_eq!(read_book("# nothing", 8), Err(ParsingError::EmptyFile));
+
+        let options = options("random random");
+        let openings = random_openings(&options);
+        assert_eq!(openings.len(), 3);
+        assert!(openings.iter().all(|o| o.ply() == 2));
+        assert_eq!(random_openings(&options), openings);
+    }
+
+    #[test]
+    fn round_robin() {
+        let mut options = options("random ai:1 ai:2");
+        let directory = tempfile::tempdir().unwrap();
+        options.csv = Some(directory.path().join("games.csv"));
+        options.json = Some(directory.path().join("games.json"));
+        let outcomes = run(&options).unwrap();
+
+        // 3 pairs play 3 openings with both colors.
+        assert_eq!(outcomes.len(), 18);
+        for a in 0..3 {
+            for b in 0..3 {
+                let games = outcomes.iter().filter(|o| o.black == a && o.white == b).count();
+                assert_eq!(games, if a == b { 0 } else { 3 });
+            }
+        }
+        assert!(outcomes.iter().all(|o| o.forfeit.is_none()));
+
+        let csv = read_to_string(options.csv.unwrap()).unwrap();
+        assert_eq!(csv.lines().count(), 19);
+        assert!(csv.lines().nth(1).unwrap().starts_with("1,0,random,ai:1,"));
+        let json = read_to_string(options.json.unwrap()).unwrap();
+        assert!(json.contains("\"participants\": [\"random\", \"ai:1\", \"ai:2\"]"));
+        assert_eq!(json.matches("\"opening\"").count(), 18);
+    }
+
+    #[test]
+    fn forfeits() {
+        let mut options = options("random engine:./no-such-engine");
+        options.openings = 1;
+        let outcomes = run(&options).unwrap();
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(outcomes[0].forfeit, Some(Player::White));
+        assert_eq!(outcomes[0].score(), 1.0);
+        assert_eq!(outcomes[1].forfeit, Some(Player::Black));
+    }
+
+    #[test]
+    fn early_stop() {
+        let mut options = options("ai:3 random");
+        options.openings = 200;
+        options.sprt = Some((-400.0, 0.0));
+        let outcomes = run(&options).unwrap();
+        assert!(outcomes.len() < 400);
+
+        // Pairs without games are left out of the results.
+        let participants = options.participants;
+        let mut records = vec![vec![Record::default(); 2]; 2];
+        let output = json(&participants, &[], &records);
+        assert!(output.contains("\"pairs\": [\n\n]"));
+        assert!(!output.contains("NaN"));
+        records[0][1] = record(1, 0, 0);
+        assert_eq!(json(&participants, &[], &records).matches("\"first\"").count(), 1);
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(csv_field("engine:a,b"), "\"engine:a,b\"");
+        assert_eq!(json_string("a \"b\"\\\n"), "\"a \\\"b\\\"\\\\\\u000a\"");
+        assert_eq!(json_number(f64::NEG_INFINITY), "null");
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{
+    board::{Board, Move, ParsingError},
+    game::Game,
+};
+
+use std::io::{ErrorKind, Read};
+
+// A WTHOR database starts with a 16-byte header, followed by fixed-size game records.
+// Numbers are little-endian.
+const HEADER_SIZE: usize = 16;
+// Each 8x8 record has 8 bytes of ids and scores followed by 60 moves.
+const RECORD_SIZE: usize = 68;
+const MOVES: usize = 60;
+
+/// The header of a WTHOR database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    /// The date the file was created, as year, month and day.
+    pub created: (u16, u8, u8),
+    /// The number of game records.
+    pub games: u32,
+    /// The year the games were played.
+    pub year: u16,
+    /// The size of the board.
+    pub size: u8,
+    /// The depth at which the theoretical scores were computed.
+    pub depth: u8,
+}
+
+/// A game record of a WTHOR database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// The id of the tournament, in the matching `.TRN` file.
+    pub tournament: u16,
+    /// The id of the black player, in the matching `.JOU` file.
+    pub black: u16,
+    /// The id of the white player, in the matching `.JOU` file.
+    pub white: u16,
+    /// The number of black discs at the end of the game.
+    pub score: u8,
+    /// The number of black discs at the end of the game with perfect play from the position
+    /// `depth` empty squares before the end.
+    pub theoretical_score: u8,
+    /// The moves played, encoded as `10 * row + column`, both starting from 1, as in 56 for F5.
+    /// Passes are not recorded.
+    pub moves: Vec<u8>,
+}
+
+/// Iterate over the game records of a WTHOR database.
+pub struct Reader<R: Read> {
+    header: Header,
+    reader: R,
+    remaining: u32,
+}
+
+impl<R: Read> Reader<R> {
+    /// Read the header of a database.
+    /// Only databases of 8x8 games are supported.
+    pub fn new(mut reader: R) -> Result<Self, ParsingError> {
+        let mut bytes = [0; HEADER_SIZE];
+        read(&mut reader, &mut bytes)?.ok_or(ParsingError::EmptyFile)?;
+        let header = Header {
+            created: (bytes[0] as u16 * 100 + bytes[1] as u16, bytes[2], bytes[3]),
+            games: u32::from_le_bytes(bytes[4..8].try_into().expect("slice of 4 bytes")),
+            year: u16::from_le_bytes(bytes[10..12].try_into().expect("slice of 2 bytes")),
+            // Older files leave the size at 0 for 8x8 games.
+            size: if bytes[12] == 0 { 8 } else { bytes[12] },
+            depth: bytes[14],
+        };
+        if header.size != 8 {
+            return Err(ParsingError::BadSize);
+        }
+
+        Ok(Self { remaining: header.games, header, reader })
+    }
+
+    /// Return the header of the database.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = Result<Record, ParsingError>;
+
+    // Stop after the number of games of the header, or at the first error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let mut bytes = [0; RECORD_SIZE];
+        match read(&mut self.reader, &mut bytes) {
+            Ok(Some(())) => { },
+            Ok(None) => {
+                self.remaining = 0;
+                return Some(Err(ParsingError::UnterminatedRecord));
+            },
+            Err(e) => {
+                self.remaining = 0;
+                return Some(Err(e));
+            },
+        }
+
+        let id = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        Some(Ok(Record {
+            tournament: id(0),
+            black: id(2),
+            white: id(4),
+            score: bytes[6],
+            theoretical_score: bytes[7],
+            moves: bytes[8..8 + MOVES].iter().copied().take_while(|&m| m != 0).collect(),
+        }))
+    }
+}
+
+impl Record {
+    /// Replay the moves of the record from the starting position.
+    /// Return `InvalidMove` for a move that is not on the board, and `IllegalMove` with the number
+    /// of the ply, passes included, for a move that cannot be played.
+    pub fn replay(&self) -> Result<Game, ParsingError> {
+        let mut game = Game::new(Board::new(8));
+        for &code in &self.moves {
+            let (row, column) = (code / 10, code % 10);
+            if !(1..=8).contains(&row) || !(1..=8).contains(&column) {
+                return Err(ParsingError::InvalidMove(code.to_string()));
+            }
+            if !game.play(&Move::new(column - 1, row - 1)) {
+                return Err(ParsingError::IllegalMove(game.ply() + 1));
+            }
+        }
+        Ok(game)
+    }
+}
+
+// Fill the buffer, returning `None` if the reader ends before.
+fn read(reader: &mut impl Read, buffer: &mut [u8]) -> Result<Option<()>, ParsingError> {
+    match reader.read_exact(buffer) {
+        Ok(()) => Ok(Some(())),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(games: u32) -> Vec<u8> {
+        let mut bytes = vec!(20, 24, 5, 17);
+        bytes.extend(games.to_le_bytes());
+        bytes.extend([0, 0]);
+        bytes.extend(2023u16.to_le_bytes());
+        bytes.extend([8, 0, 22, 0]);
+        bytes
+    }
+
+    fn record(tournament: u16, moves: &[u8]) -> Vec<u8> {
+        let mut bytes = vec!();
+        bytes.extend(tournament.to_le_bytes());
+        bytes.extend(1u16.to_le_bytes());
+        bytes.extend(2u16.to_le_bytes());
+        bytes.extend([36, 32]);
+        bytes.extend(moves);
+        bytes.resize(RECORD_SIZE, 0);
+        bytes
+    }
+
+    #[test]
+    fn reading() {
+        let mut bytes = header(2);
+        bytes.extend(record(7, &[56, 64, 33]));
+        bytes.extend(record(8, &[]));
+        let mut reader = Reader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.header(), &Header { created: (2024, 5, 17), games: 2, year: 2023, size: 8, depth: 22 });
+
+        let record = reader.next().unwrap().unwrap();
+        assert_eq!((record.tournament, record.black, record.white), (7, 1, 2));
+        assert_eq!((record.score, record.theoretical_score), (36, 32));
+        assert_eq!(record.moves, vec!(56, 64, 33));
+        assert_eq!(reader.next().unwrap().unwrap().moves, vec!());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(Reader::new(&[20u8, 24][..]).err(), Some(ParsingError::EmptyFile));
+
+        let mut bytes = header(3);
+        bytes.extend(record(7, &[56]));
+        bytes.extend(&record(8, &[56])[..10]);
+        let reader = Reader::new(bytes.as_slice()).unwrap();
+        let records: Vec<_> = reader.collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1], Err(ParsingError::UnterminatedRecord));
+
+        let mut bytes = header(0);
+        bytes[12] = 10;
+        assert_eq!(Reader::new(bytes.as_slice()).err(), Some(ParsingError::BadSize));
+    }
+
+    #[test]
+    fn replay() {
+        let bytes = [header(1), record(0, &[56, 64, 33])].concat();
+        let mut reader = Reader::new(bytes.as_slice()).unwrap();
+        let game = reader.next().unwrap().unwrap().replay().unwrap();
+        assert_eq!(game.plies(), &[Some(Move::new(5, 4)), Some(Move::new(3, 5)), Some(Move::new(2, 2))]);
+
+        let record = |moves: Vec<u8>| Record { tournament: 0, black: 0, white: 0, score: 0, theoretical_score: 0, moves };
+        assert_eq!(record(vec!(56, 64, 11)).replay(), Err(ParsingError::IllegalMove(3)));
+        assert_eq!(record(vec!(56, 90)).replay(), Err(ParsingError::InvalidMove("90".into())));
+        assert_eq!(record(vec!(56, 19)).replay(), Err(ParsingError::InvalidMove("19".into())));
+    }
+}
@@ -0,0 +1,35 @@
+use rustversi::board::Board;
+
+use std::process::Command;
+
+// Run the binary in contest mode with the given arguments, and return its output.
+fn run(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_rustversi"))
+        .args(["--contest", "--timeout", "1"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn text() {
+    let output = run(&["--size", "2"]);
+    assert!(Board::new(4).is_valid_move(&output.trim().parse().unwrap()));
+}
+
+#[test]
+fn json() {
+    let output = run(&["--json", "--size", "2"]);
+    let m = output.strip_prefix("{\"move\":\"").unwrap().split('"').next().unwrap();
+    assert!(Board::new(4).is_valid_move(&m.parse().unwrap()));
+}
+
+#[test]
+fn finished_game() {
+    // The discs of the start fill a 2x2 board: the game is over before it starts.
+    assert_eq!(run(&["--shape", "2x2"]), "The game is over, there is no move to play.\n");
+    let output = run(&["--json", "--shape", "2x2"]);
+    assert!(output.starts_with("{\"move\":null,\"score\":null,"), "{}", output);
+}
@@ -0,0 +1,9 @@
+#!/bin/sh
+# A stub engine answering D3 late from the starting position, and C5 at once from any other.
+while read -r line; do
+    case "$line" in
+        moves) start=true ;;
+        moves*) start=false ;;
+        go*) if [ "$start" = true ]; then sleep 1; echo D3; else echo C5; fi ;;
+    esac
+done
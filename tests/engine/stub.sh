@@ -0,0 +1,6 @@
+#!/bin/sh
+# A stub engine answering its arguments as moves, in turn, then exiting.
+for m in "$@"; do
+    while read -r line && [ "${line%% *}" != go ]; do :; done
+    echo "$m"
+done
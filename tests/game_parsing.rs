@@ -0,0 +1,82 @@
+use rustversi::{board::{Board, ParsingError}, game::Game};
+
+use std::fs::File;
+
+// The game played in most of the transcripts.
+fn short_game() -> Game {
+    let mut game = Game::new(Board::new(4));
+    for m in ["B1", "A1", "A2"] {
+        assert!(game.play(&m.parse().unwrap()));
+    }
+    game
+}
+
+#[test]
+fn transcript_pass() {
+    let mut game = short_game();
+    game.black = Some("Alice".into());
+    game.white = Some("Bob".into());
+    game.result = Some("unfinished".into());
+
+    let file = File::open("tests/game_parsing/game-transcript.pass").unwrap();
+    assert_eq!(Game::try_from(file), Ok(game));
+}
+
+#[test]
+fn board_only_pass() {
+    let file = File::open("tests/game_parsing/game-board_only.pass").unwrap();
+    assert_eq!(Game::try_from(file), Ok(Game::new(Board::new(8))));
+}
+
+#[test]
+fn moves_on_several_lines_pass() {
+    let file = File::open("tests/game_parsing/game-moves_on_several_lines.pass").unwrap();
+    assert_eq!(Game::try_from(file), Ok(short_game()));
+}
+
+#[test]
+fn unknown_field_fail() {
+    let file = File::open("tests/game_parsing/game-unknown_field.fail").unwrap();
+    assert_eq!(Game::try_from(file), Err(ParsingError::UnknownField("Referee".into())));
+}
+
+#[test]
+fn invalid_move_fail() {
+    let file = File::open("tests/game_parsing/game-invalid_move.fail").unwrap();
+    assert_eq!(Game::try_from(file), Err(ParsingError::InvalidMove("11".into())));
+}
+
+#[test]
+fn illegal_move_fail() {
+    let file = File::open("tests/game_parsing/game-illegal_move.fail").unwrap();
+    assert_eq!(Game::try_from(file), Err(ParsingError::IllegalMove(3)));
+}
+
+#[test]
+fn missing_board_fail() {
+    let file = File::open("tests/game_parsing/game-missing_board.fail").unwrap();
+    assert_eq!(Game::try_from(file), Err(ParsingError::EmptyFile));
+}
+
+#[test]
+fn header_after_board_fail() {
+    let file = File::open("tests/game_parsing/game-header_after_board.fail").unwrap();
+    assert_eq!(Game::try_from(file), Err(ParsingError::InvalidCharacter('M')));
+}
+
+#[test]
+fn ggf_pass() {
+    let file = File::open("tests/game_parsing/game-ggf.pass").unwrap();
+    let game = Game::try_from(file).unwrap();
+    assert_eq!(game.black.as_deref(), Some("alice"));
+    assert_eq!(game.time_control.as_deref(), Some("05:00//02:00"));
+    assert_eq!(game.ply(), 3);
+    assert_eq!(game.start(), &Board::new(8));
+}
+
+#[test]
+fn ggf_bad_board_fail() {
+    let file = File::open("tests/game_parsing/game-ggf_bad_board.fail").unwrap();
+    let board = "8 -------- -------- -------- ---O*--- ---*O--- -------- -------- *";
+    assert_eq!(Game::try_from(file), Err(ParsingError::InvalidField("BO".into(), board.into())));
+}
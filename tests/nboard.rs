@@ -0,0 +1,67 @@
+use rustversi::{board::Board, game::Game};
+
+use std::{
+    fs::{File, read_to_string},
+    process::{Command, Stdio},
+};
+
+// Pipe a transcript of commands through the binary in NBoard mode and return its output.
+fn run(transcript: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_rustversi"))
+        .args(["--protocol", "nboard", "--timeout", "1"])
+        .stdin(Stdio::from(File::open(transcript).unwrap()))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn handshake() {
+    assert_eq!(run("tests/nboard/handshake.in"), read_to_string("tests/nboard/handshake.out").unwrap());
+}
+
+#[test]
+fn game() {
+    let output = run("tests/nboard/game.in");
+    let mut lines = output.lines().filter(|l| !l.starts_with("status"));
+    assert_eq!(lines.next(), Some("set myname rustversi"));
+    assert_eq!(lines.next(), Some("pong 1"));
+
+    // The engine plays white after F5, then black after the GUI plays D6 and C3.
+    let mut game = Game::new(Board::new(8));
+    game.play_sequence("f5").unwrap();
+    let first = lines.next().unwrap().strip_prefix("=== ").unwrap().parse().unwrap();
+    assert!(game.board().is_valid_move(&first));
+    game.play_sequence("d6c3").unwrap();
+    assert_eq!(lines.next(), Some("pong 2"));
+
+    let hint: Vec<&str> = lines.next().unwrap().split(' ').collect();
+    assert_eq!(hint[0], "search");
+    assert!(game.board().is_valid_move(&hint[1].parse().unwrap()));
+    let second = lines.next().unwrap().strip_prefix("=== ").unwrap().parse().unwrap();
+    assert!(game.board().is_valid_move(&second));
+    assert_eq!(lines.next(), Some("pong 3"));
+    assert_eq!(lines.next(), None);
+}
+
+#[test]
+fn hint() {
+    let output = run("tests/nboard/hint.in");
+    let hints: Vec<Vec<&str>> = output.lines().filter(|l| l.starts_with("search")).map(|l| l.split(' ').collect()).collect();
+    assert_eq!(hints.len(), 3);
+    for hint in &hints {
+        assert!(Board::new(8).is_valid_move(&hint[1].parse().unwrap()));
+        assert_eq!(hint[4], "2");
+    }
+    assert!(hints[0][1] != hints[1][1] && hints[1][1] != hints[2][1] && hints[0][1] != hints[2][1]);
+    assert!(output.ends_with("status\npong 1\n"));
+}
+
+#[test]
+fn won() {
+    // Black wins by taking the only white disc: the evaluation is the final disc difference.
+    let output = run("tests/nboard/won.in");
+    let lines: Vec<&str> = output.lines().filter(|l| !l.starts_with("status")).collect();
+    assert_eq!(lines, ["set myname rustversi", "search F4 3 0 1", "=== F4", "pong 1"]);
+}
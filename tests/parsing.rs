@@ -30,9 +30,9 @@ fn board_2x2_pass() {
 }
 
 #[test]
-fn board_3x3_fail() {
-    let file = File::open("tests/board_parsing/board-3x3.fail").unwrap();
-    assert_eq!(Board::try_from(file), Err(ParsingError::BadSize));
+fn board_3x3_pass() {
+    let file = File::open("tests/board_parsing/board-3x3.pass").unwrap();
+    assert_eq!(Board::try_from(file), Ok(Board::new(3)));
 }
 
 #[test]
@@ -42,9 +42,9 @@ fn board_4x4_pass() {
 }
 
 #[test]
-fn board_5x5_fail() {
-    let file = File::open("tests/board_parsing/board-5x5.fail").unwrap();
-    assert_eq!(Board::try_from(file), Err(ParsingError::BadSize));
+fn board_5x5_pass() {
+    let file = File::open("tests/board_parsing/board-5x5.pass").unwrap();
+    assert_eq!(Board::try_from(file), Ok(Board::new(5)));
 }
 
 #[test]
@@ -53,10 +53,14 @@ fn board_6x6_pass() {
     assert_eq!(Board::try_from(file), Ok(Board::new(6)));
 }
 
+// Odd boards need not be in their starting position.
 #[test]
-fn board_7x7_fail() {
-    let file = File::open("tests/board_parsing/board-7x7.fail").unwrap();
-    assert_eq!(Board::try_from(file), Err(ParsingError::BadSize));
+fn board_7x7_pass() {
+    let file = File::open("tests/board_parsing/board-7x7.pass").unwrap();
+    let board = Board::try_from(file).unwrap();
+    assert_eq!((board.width(), board.height()), (7, 7));
+    assert_eq!(board.get(3, 4), Square::Disc(Player::White));
+    assert_eq!(board.get(4, 4), Square::Disc(Player::Black));
 }
 
 #[test]
@@ -66,9 +70,9 @@ fn board_8x8_pass() {
 }
 
 #[test]
-fn board_9x9_fail() {
-    let file = File::open("tests/board_parsing/board-9x9.fail").unwrap();
-    assert_eq!(Board::try_from(file), Err(ParsingError::BadSize));
+fn board_9x9_pass() {
+    let file = File::open("tests/board_parsing/board-9x9.pass").unwrap();
+    assert_eq!(Board::try_from(file), Ok(Board::new(9)));
 }
 
 #[test]
@@ -78,15 +82,37 @@ fn board_10x10_pass() {
 }
 
 #[test]
-fn board_11x11_fail() {
-    let file = File::open("tests/board_parsing/board-11x11.fail").unwrap();
-    assert_eq!(Board::try_from(file), Err(ParsingError::BadSize));
+fn board_11x11_pass() {
+    let file = File::open("tests/board_parsing/board-11x11.pass").unwrap();
+    let board = Board::try_from(file).unwrap();
+    assert_eq!((board.width(), board.height()), (11, 11));
+    assert_eq!(board.get(4, 4), Square::Disc(Player::White));
+    assert_eq!(board.get(5, 4), Square::Disc(Player::Black));
 }
 
 #[test]
-fn board_12x12_fail() {
-    let file = File::open("tests/board_parsing/board-12x12.fail").unwrap();
-    assert_eq!(Board::try_from(file), Err(ParsingError::BadSize));
+fn board_12x11_pass() {
+    let file = File::open("tests/board_parsing/board-12x11.pass").unwrap();
+    let board = Board::try_from(file).unwrap();
+    assert_eq!((board.width(), board.height()), (12, 11));
+    assert_eq!(board.get(5, 4), Square::Disc(Player::White));
+}
+
+#[test]
+fn board_14x14_pass() {
+    let file = File::open("tests/board_parsing/board-14x14.pass").unwrap();
+    assert_eq!(Board::try_from(file), Ok(Board::new(14)));
+}
+
+// Boards may have more columns than rows, as long as both are between 2 and 14.
+#[test]
+fn board_8x4_pass() {
+    let file = File::open("tests/board_parsing/board-8x4.pass").unwrap();
+    let board = Board::try_from(file).unwrap();
+    assert_eq!((board.width(), board.height()), (8, 4));
+    assert_eq!(board.get(3, 3), Square::Disc(Player::White));
+    assert_eq!(board.get(4, 3), Square::Disc(Player::Black));
+    assert_eq!(board.player, Some(Player::Black));
 }
 
 #[test]
@@ -142,6 +168,7 @@ fn eof_after_current_player_fail() {
     assert_eq!(Board::try_from(file), Err(ParsingError::BadSize));
 }
 
+// The file ends in the middle of the fifth row.
 #[test]
 fn eof_before_end_of_the_board_fail() {
     let file = File::open("tests/board_parsing/board-eof_before_end_of_the_board.fail").unwrap();
@@ -0,0 +1,39 @@
+use rustversi::{board::Board, computer::Patterns, game::Game};
+
+use std::{fs::write, process::Command};
+
+// Run the binary with the given arguments, and return its standard output and error.
+fn run(args: &[&str]) -> (bool, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_rustversi")).args(args).output().unwrap();
+    (output.status.success(), String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap())
+}
+
+#[test]
+fn weights() {
+    let directory = tempfile::tempdir().unwrap();
+    let file = directory.path().join("weights.bin");
+    Patterns::new(4, 1).save(&file).unwrap();
+    let file = file.to_str().unwrap();
+
+    assert!(run(&["--weights", file, "--contest", "--size", "2"]).0);
+    let (success, output) = run(&["--weights", file, "--contest", "--shape", "6x4"]);
+    assert!(!success);
+    assert!(output.contains("InvalidField(\"shape\", \"6x4\")"), "{}", output);
+    let (success, output) = run(&["tournament", &format!("patterns:{}", file), "random", "--size", "3"]);
+    assert!(!success);
+    assert!(output.contains("InvalidField(\"shape\", \"6x6\")"), "{}", output);
+}
+
+#[test]
+fn book() {
+    let directory = tempfile::tempdir().unwrap();
+    for (shape, board) in [("12x12", Board::new(12)), ("6x8", Board::rectangular(6, 8))] {
+        let games = directory.path().join("games.ggf");
+        write(&games, Game::new(board).to_ggf()).unwrap();
+        let book = directory.path().join("book.bin");
+        let (success, output) = run(&["book", book.to_str().unwrap(), games.to_str().unwrap()]);
+        assert!(!success);
+        assert!(output.contains(&format!("InvalidField(\"shape\", \"{}\")", shape)), "{}", output);
+        assert!(!book.exists());
+    }
+}